@@ -0,0 +1,23 @@
+// 提取前端源码中的 GraphQL 操作，生成 src-tauri/persisted-queries.json（sha256 -> 查询文本），
+// 后端在编译时嵌入该清单，启用 `persisted-queries-only` 特性的 release 构建只执行清单中的查询
+import { createHash } from 'node:crypto';
+import { readdirSync, readFileSync, writeFileSync } from 'node:fs';
+import { join } from 'node:path';
+
+const root = new URL('..', import.meta.url).pathname;
+const sources = readdirSync(join(root, 'src'), { recursive: true })
+    .filter(file => /\.(ts|tsx)$/.test(file))
+    .map(file => readFileSync(join(root, 'src', file), 'utf8'));
+
+// 与运行时发送的文本逐字节一致，哈希才能匹配
+const operation = /([`'])(\s*(?:query|mutation|subscription)\b[^`']*)\1/g;
+const manifest = {};
+for (const source of sources) {
+    for (const [, , query] of source.matchAll(operation)) {
+        manifest[createHash('sha256').update(query).digest('hex')] = query;
+    }
+}
+
+const sorted = Object.fromEntries(Object.entries(manifest).sort(([a], [b]) => a.localeCompare(b)));
+writeFileSync(join(root, 'src-tauri', 'persisted-queries.json'), JSON.stringify(sorted, null, 2) + '\n');
+console.log(`Registered ${Object.keys(sorted).length} persisted queries`);
@@ -1,3 +1,5 @@
 fn main() {
+    // 迁移通过 sqlx::migrate! 嵌入，新增迁移文件时需要重新编译
+    println!("cargo:rerun-if-changed=migrations");
     tauri_build::build()
 }
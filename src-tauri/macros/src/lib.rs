@@ -0,0 +1,293 @@
+//! 分页连接相关的派生宏，生成的代码引用 `crate::graphql::relay`，只能在应用 crate 内使用
+
+use proc_macro::TokenStream;
+use proc_macro2::Span;
+use quote::{format_ident, quote};
+use syn::{
+    parse_macro_input, spanned::Spanned, Data, DeriveInput, Error, Fields, Ident, LitStr, Result,
+    Type,
+};
+
+/// 为节点类型实现 `relay::ConnectionNode`
+///
+/// ```ignore
+/// #[derive(ConnectionNode)]
+/// #[connection(cursor = "id, created_at, description, done, updated_at")]
+/// pub struct Todo { ... }
+/// ```
+///
+/// - `cursor`：可以作为排序键写入游标的字段，`id` 字段总是作为决胜键写入，列出与否均可；
+///   `order` 的 `ConnectionOrder::KEYS` 必须都在其中，否则编译失败
+/// - `order`：可选，排序方式的类型，默认为 `Vec<{结构体名}Order>`
+/// - `name`：可选，GraphQL 类型名，默认为结构体名，连接和边的类型名由它加上
+///   `Connection`/`Edge`/`OffsetConnection` 构成
+/// - `fields`：可选，连接上的附加字段，类型需要派生 `ConnectionFields`
+#[proc_macro_derive(ConnectionNode, attributes(connection))]
+pub fn derive_connection_node(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_connection_node(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+/// 为结构体实现 `relay::ConnectionFields`，每个字段对应连接上的一个同名（camelCase）字段，
+/// 文档注释作为字段描述
+#[proc_macro_derive(ConnectionFields)]
+pub fn derive_connection_fields(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_connection_fields(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+struct ConnectionAttr {
+    order: Option<Type>,
+    cursor: Vec<Ident>,
+    name: String,
+    fields: Option<Type>,
+}
+
+impl ConnectionAttr {
+    fn parse(input: &DeriveInput) -> Result<Self> {
+        let mut order = None;
+        let mut cursor = None;
+        let mut name = None;
+        let mut fields = None;
+        for attr in input
+            .attrs
+            .iter()
+            .filter(|a| a.path().is_ident("connection"))
+        {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("order") {
+                    order = Some(meta.value()?.parse::<Type>()?);
+                } else if meta.path.is_ident("cursor") {
+                    let list = meta.value()?.parse::<LitStr>()?;
+                    cursor = Some(
+                        list.value()
+                            .split(',')
+                            .map(str::trim)
+                            .filter(|it| !it.is_empty())
+                            .map(|it| {
+                                syn::parse_str::<Ident>(it).map(|_| Ident::new(it, list.span()))
+                            })
+                            .collect::<Result<Vec<_>>>()?,
+                    );
+                } else if meta.path.is_ident("name") {
+                    name = Some(meta.value()?.parse::<LitStr>()?.value());
+                } else if meta.path.is_ident("fields") {
+                    fields = Some(meta.value()?.parse::<Type>()?);
+                } else {
+                    return Err(meta.error("expected `order`, `cursor`, `name` or `fields`"));
+                }
+                Ok(())
+            })?;
+        }
+        let missing = |key: &str| {
+            Error::new(
+                Span::call_site(),
+                format!("missing `#[connection({key} = ...)]`"),
+            )
+        };
+        Ok(Self {
+            order,
+            cursor: cursor.ok_or_else(|| missing("cursor"))?,
+            name: name.unwrap_or_else(|| input.ident.to_string()),
+            fields,
+        })
+    }
+}
+
+fn named_fields(input: &DeriveInput) -> Result<&syn::FieldsNamed> {
+    match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => Ok(fields),
+            _ => Err(Error::new(
+                input.span(),
+                "expected a struct with named fields",
+            )),
+        },
+        _ => Err(Error::new(
+            input.span(),
+            "expected a struct with named fields",
+        )),
+    }
+}
+
+fn expand_connection_node(input: DeriveInput) -> Result<proc_macro2::TokenStream> {
+    let attr = ConnectionAttr::parse(&input)?;
+    let fields = named_fields(&input)?;
+    for key in &attr.cursor {
+        if !fields.named.iter().any(|f| f.ident.as_ref() == Some(key)) {
+            return Err(Error::new(
+                key.span(),
+                format!("`{key}` is not a field of `{}`", input.ident),
+            ));
+        }
+    }
+
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let order = attr.order.as_ref().map_or_else(
+        || {
+            let order = format_ident!("{}Order", ident);
+            quote!(::std::vec::Vec<#order>)
+        },
+        |it| quote!(#it),
+    );
+    let connection_fields = attr
+        .fields
+        .as_ref()
+        .map_or_else(|| quote!(()), |it| quote!(#it));
+    let connection_type_name = format!("{}Connection", attr.name);
+    let edge_type_name = format!("{}Edge", attr.name);
+    let offset_connection_type_name = format!("{}OffsetConnection", attr.name);
+    let mut cursor = attr.cursor.clone();
+    if !cursor.iter().any(|it| it == "id") {
+        cursor.insert(0, Ident::new("id", Span::call_site()));
+    }
+    let keys = cursor.iter().map(Ident::to_string).collect::<Vec<_>>();
+    let unknown = format!("Order key '{{}}' is not a cursor field of {}", attr.name);
+    let missing = format!(
+        "the order of {} has keys not listed in #[connection(cursor = ...)]",
+        attr.name
+    );
+
+    Ok(quote! {
+        // 在编译期检查排序方式的每个键都能写入游标
+        const _: () = {
+            const fn eq(a: &str, b: &str) -> bool {
+                let (a, b) = (a.as_bytes(), b.as_bytes());
+                if a.len() != b.len() {
+                    return false;
+                }
+                let mut i = 0;
+                while i < a.len() {
+                    if a[i] != b[i] {
+                        return false;
+                    }
+                    i += 1;
+                }
+                true
+            }
+
+            const CURSOR: &[&str] = &[#(#keys),*];
+            let keys = <#order as crate::graphql::relay::ConnectionOrder>::KEYS;
+            let mut i = 0;
+            while i < keys.len() {
+                let mut j = 0;
+                while j < CURSOR.len() && !eq(keys[i], CURSOR[j]) {
+                    j += 1;
+                }
+                assert!(j < CURSOR.len(), #missing);
+                i += 1;
+            }
+        };
+
+        impl #impl_generics crate::graphql::relay::ConnectionNode for #ident #ty_generics #where_clause {
+            type Order = #order;
+            type ConnectionFields = #connection_fields;
+            const CONNECTION_TYPE_NAME: &'static str = #connection_type_name;
+            const EDGE_TYPE_NAME: &'static str = #edge_type_name;
+            const OFFSET_CONNECTION_TYPE_NAME: &'static str = #offset_connection_type_name;
+
+            fn cursor(&self, order: &Self::Order) -> crate::graphql::relay::Cursor {
+                use crate::graphql::relay::{ConnectionOrder, Cursor, CursorValue};
+
+                let values = order
+                    .keys()
+                    .into_iter()
+                    .map(|key| match key {
+                        #(#keys => CursorValue::from(&self.#cursor),)*
+                        // 排序方式与节点在同一处定义，出现未声明的键属于编程错误
+                        key => unreachable!(#unknown, key),
+                    })
+                    .collect();
+                Cursor::new(Self::CONNECTION_TYPE_NAME, order.signature(), values, self.id)
+            }
+        }
+    })
+}
+
+fn expand_connection_fields(input: DeriveInput) -> Result<proc_macro2::TokenStream> {
+    let fields = named_fields(&input)?;
+    let ident = &input.ident;
+    let mut registrations = Vec::new();
+    let mut resolvers = Vec::new();
+    let mut names = Vec::new();
+    for field in &fields.named {
+        let field_ident = field.ident.as_ref().unwrap();
+        let ty = &field.ty;
+        let name = camel_case(&field_ident.to_string());
+        let description = field
+            .attrs
+            .iter()
+            .filter(|a| a.path().is_ident("doc"))
+            .filter_map(|a| match &a.meta {
+                syn::Meta::NameValue(nv) => match &nv.value {
+                    syn::Expr::Lit(syn::ExprLit {
+                        lit: syn::Lit::Str(s),
+                        ..
+                    }) => Some(s.value().trim().to_string()),
+                    _ => None,
+                },
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        let registration = if description.is_empty() {
+            quote!(registry.field::<&#ty>(#name, &()))
+        } else {
+            quote!(registry.field::<&#ty>(#name, &()).description(#description))
+        };
+        registrations.push(registration);
+        resolvers.push(quote!(#name => Some(executor.resolve_with_ctx(&(), &self.#field_ident)),));
+        names.push(name);
+    }
+
+    Ok(quote! {
+        impl crate::graphql::relay::ConnectionFields for #ident {
+            const FIELD_NAMES: &'static [&'static str] = &[#(#names),*];
+
+            fn fields<'r, S>(
+                registry: &mut juniper::Registry<'r, S>,
+            ) -> Vec<juniper::meta::Field<'r, S>>
+            where
+                S: juniper::ScalarValue + 'r,
+            {
+                vec![#(#registrations),*]
+            }
+
+            fn resolve_field<C, S>(
+                &self,
+                field_name: &str,
+                executor: &juniper::Executor<C, S>,
+            ) -> Option<juniper::ExecutionResult<S>>
+            where
+                S: juniper::ScalarValue,
+            {
+                match field_name {
+                    #(#resolvers)*
+                    _ => None,
+                }
+            }
+        }
+    })
+}
+
+/// `active_count` -> `activeCount`，与 juniper 的字段命名规则一致
+fn camel_case(name: &str) -> String {
+    let mut result = String::with_capacity(name.len());
+    let mut upper = false;
+    for c in name.trim_start_matches("r#").chars() {
+        if c == '_' {
+            upper = !result.is_empty();
+        } else if upper {
+            result.extend(c.to_uppercase());
+            upper = false;
+        } else {
+            result.push(c);
+        }
+    }
+    result
+}
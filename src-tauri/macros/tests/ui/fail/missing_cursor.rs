@@ -0,0 +1,13 @@
+use tauri_graphql_demo_macros::ConnectionNode;
+
+include!("../relay.rs");
+
+#[derive(ConnectionNode)]
+#[connection(order = WidgetOrder)]
+pub struct Widget {
+    id: i64,
+    name: String,
+    created_at: i64,
+}
+
+fn main() {}
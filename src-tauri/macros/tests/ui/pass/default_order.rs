@@ -0,0 +1,24 @@
+use graphql::relay::{ConnectionNode, ConnectionOrder};
+use tauri_graphql_demo_macros::ConnectionNode;
+
+include!("../relay.rs");
+
+#[derive(ConnectionNode)]
+#[connection(cursor = "id, name, created_at")]
+pub struct Widget {
+    id: i64,
+    name: String,
+    created_at: i64,
+}
+
+fn main() {
+    let widget = Widget {
+        id: 1,
+        name: "widget".to_owned(),
+        created_at: 0,
+    };
+    let order: Vec<WidgetOrder> = vec![WidgetOrder::Name, WidgetOrder::CreatedAt];
+    assert_eq!(order.keys(), ["name", "created_at"]);
+    widget.cursor(&order);
+    assert_eq!(Widget::CONNECTION_TYPE_NAME, "WidgetConnection");
+}
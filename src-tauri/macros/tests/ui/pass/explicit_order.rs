@@ -0,0 +1,24 @@
+use graphql::relay::ConnectionNode;
+use tauri_graphql_demo_macros::ConnectionNode;
+
+include!("../relay.rs");
+
+// `id` 可以省略，`order` 和 `name` 可以显式指定
+#[derive(ConnectionNode)]
+#[connection(order = WidgetOrder, cursor = "created_at, name", name = "Gadget")]
+pub struct Widget {
+    id: i64,
+    name: String,
+    created_at: i64,
+}
+
+fn main() {
+    let widget = Widget {
+        id: 1,
+        name: "widget".to_owned(),
+        created_at: 0,
+    };
+    widget.cursor(&WidgetOrder::CreatedAt);
+    assert_eq!(Widget::EDGE_TYPE_NAME, "GadgetEdge");
+    assert_eq!(Widget::OFFSET_CONNECTION_TYPE_NAME, "GadgetOffsetConnection");
+}
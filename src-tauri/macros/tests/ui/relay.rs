@@ -0,0 +1,75 @@
+// `#[derive(ConnectionNode)]` 展开后引用的 `crate::graphql::relay` 的最小替身，由各用例 `include!`
+
+#[allow(dead_code)]
+mod graphql {
+    pub mod relay {
+        pub struct Cursor;
+
+        impl Cursor {
+            pub fn new(_: &str, _: String, _: Vec<CursorValue>, _: i64) -> Self {
+                Self
+            }
+        }
+
+        pub struct CursorValue;
+
+        impl<T> From<&T> for CursorValue {
+            fn from(_: &T) -> Self {
+                Self
+            }
+        }
+
+        pub trait ConnectionOrder {
+            const KEYS: &'static [&'static str];
+            fn signature(&self) -> String;
+            fn keys(&self) -> Vec<&'static str>;
+        }
+
+        impl<T: ConnectionOrder> ConnectionOrder for Vec<T> {
+            const KEYS: &'static [&'static str] = T::KEYS;
+
+            fn signature(&self) -> String {
+                String::new()
+            }
+
+            fn keys(&self) -> Vec<&'static str> {
+                self.iter().flat_map(ConnectionOrder::keys).collect()
+            }
+        }
+
+        pub trait ConnectionFields {}
+
+        impl ConnectionFields for () {}
+
+        pub trait ConnectionNode {
+            type Order: ConnectionOrder;
+            type ConnectionFields: ConnectionFields;
+            fn cursor(&self, order: &Self::Order) -> Cursor;
+            const CONNECTION_TYPE_NAME: &'static str;
+            const EDGE_TYPE_NAME: &'static str;
+            const OFFSET_CONNECTION_TYPE_NAME: &'static str;
+        }
+    }
+}
+
+/// 按 `name` 或 `created_at` 排序
+#[allow(dead_code)]
+pub enum WidgetOrder {
+    Name,
+    CreatedAt,
+}
+
+impl graphql::relay::ConnectionOrder for WidgetOrder {
+    const KEYS: &'static [&'static str] = &["name", "created_at"];
+
+    fn signature(&self) -> String {
+        String::new()
+    }
+
+    fn keys(&self) -> Vec<&'static str> {
+        match self {
+            Self::Name => vec!["name"],
+            Self::CreatedAt => vec!["created_at"],
+        }
+    }
+}
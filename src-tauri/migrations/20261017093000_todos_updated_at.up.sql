@@ -0,0 +1,18 @@
+-- SQLite 不允许在非空表上追加带非常量默认值的列，因此重建表
+CREATE TABLE todos_new
+(
+    id          INTEGER PRIMARY KEY NOT NULL,
+    description TEXT                NOT NULL,
+    done        BOOLEAN             NOT NULL DEFAULT 0,
+    created_at  INTEGER             NOT NULL DEFAULT (UNIXEPOCH(CURRENT_TIMESTAMP)),
+    updated_at  INTEGER             NOT NULL DEFAULT (UNIXEPOCH(CURRENT_TIMESTAMP))
+);
+
+INSERT INTO todos_new (id, description, done, created_at, updated_at)
+SELECT id, description, done, created_at, created_at
+FROM todos;
+
+DROP TABLE todos;
+
+ALTER TABLE todos_new
+RENAME TO todos;
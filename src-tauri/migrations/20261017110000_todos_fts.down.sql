@@ -0,0 +1,4 @@
+DROP TRIGGER todos_fts_after_update;
+DROP TRIGGER todos_fts_after_delete;
+DROP TRIGGER todos_fts_after_insert;
+DROP TABLE todos_fts;
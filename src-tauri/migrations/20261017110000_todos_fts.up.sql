@@ -0,0 +1,26 @@
+-- trigram 分词器按子串匹配，对中文等没有空格分词的文本同样有效
+CREATE VIRTUAL TABLE todos_fts USING fts5
+(
+    description,
+    content = 'todos',
+    content_rowid = 'id',
+    tokenize = 'trigram'
+);
+
+CREATE TRIGGER todos_fts_after_insert AFTER INSERT ON todos
+BEGIN
+    INSERT INTO todos_fts (rowid, description) VALUES (NEW.id, NEW.description);
+END;
+
+CREATE TRIGGER todos_fts_after_delete AFTER DELETE ON todos
+BEGIN
+    INSERT INTO todos_fts (todos_fts, rowid, description) VALUES ('delete', OLD.id, OLD.description);
+END;
+
+CREATE TRIGGER todos_fts_after_update AFTER UPDATE OF description ON todos
+BEGIN
+    INSERT INTO todos_fts (todos_fts, rowid, description) VALUES ('delete', OLD.id, OLD.description);
+    INSERT INTO todos_fts (rowid, description) VALUES (NEW.id, NEW.description);
+END;
+
+INSERT INTO todos_fts (todos_fts) VALUES ('rebuild');
@@ -0,0 +1,4 @@
+DROP INDEX todos_position;
+
+ALTER TABLE todos
+DROP COLUMN position;
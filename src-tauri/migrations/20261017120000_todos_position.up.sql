@@ -0,0 +1,8 @@
+-- 手动排序的位置，按间隔 1024 分配，移动时取相邻两项的中点，间隔用尽时整体重排
+ALTER TABLE todos
+ADD COLUMN position INTEGER NOT NULL DEFAULT 0;
+
+UPDATE todos
+SET position = id * 1024;
+
+CREATE INDEX todos_position ON todos (position, id);
@@ -0,0 +1,10 @@
+DROP INDEX todos_pending_reminders;
+
+ALTER TABLE todos
+DROP COLUMN reminded;
+
+ALTER TABLE todos
+DROP COLUMN remind_at;
+
+ALTER TABLE todos
+DROP COLUMN due_at;
@@ -0,0 +1,11 @@
+-- 截止时间与提醒时间（Unix 秒），reminded 记录提醒是否已经发出，修改 remind_at 时重置
+ALTER TABLE todos
+ADD COLUMN due_at INTEGER;
+
+ALTER TABLE todos
+ADD COLUMN remind_at INTEGER;
+
+ALTER TABLE todos
+ADD COLUMN reminded BOOLEAN NOT NULL DEFAULT 0;
+
+CREATE INDEX todos_pending_reminders ON todos (remind_at) WHERE reminded = 0;
@@ -0,0 +1,3 @@
+DROP INDEX todo_tags_tag_id;
+DROP TABLE todo_tags;
+DROP TABLE tags;
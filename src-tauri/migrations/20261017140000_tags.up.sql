@@ -0,0 +1,17 @@
+-- 标签名不区分大小写地唯一；删除事项或标签时一并删除关联
+CREATE TABLE tags
+(
+    id         INTEGER PRIMARY KEY NOT NULL,
+    name       TEXT                NOT NULL UNIQUE COLLATE NOCASE CHECK ( TRIM(name) <> '' ),
+    created_at INTEGER             NOT NULL DEFAULT (UNIXEPOCH(CURRENT_TIMESTAMP))
+);
+
+CREATE TABLE todo_tags
+(
+    todo_id    INTEGER NOT NULL REFERENCES todos (id) ON DELETE CASCADE,
+    tag_id     INTEGER NOT NULL REFERENCES tags (id) ON DELETE CASCADE,
+    created_at INTEGER NOT NULL DEFAULT (UNIXEPOCH(CURRENT_TIMESTAMP)),
+    PRIMARY KEY (todo_id, tag_id)
+);
+
+CREATE INDEX todo_tags_tag_id ON todo_tags (tag_id, todo_id);
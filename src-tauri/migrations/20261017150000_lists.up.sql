@@ -0,0 +1,20 @@
+CREATE TABLE lists
+(
+    id         INTEGER PRIMARY KEY NOT NULL,
+    name       TEXT                NOT NULL CHECK ( TRIM(name) <> '' ),
+    -- #RRGGBB，可以为空
+    color      TEXT,
+    archived   BOOLEAN             NOT NULL DEFAULT 0,
+    created_at INTEGER             NOT NULL DEFAULT (UNIXEPOCH(CURRENT_TIMESTAMP))
+);
+
+-- 默认清单，未指定清单的事项都归入这里，id 固定为 1
+INSERT INTO lists (id, name)
+VALUES (1, 'Inbox');
+
+-- 启用外键时 ADD COLUMN 不允许带非空默认值的 REFERENCES，而重建 todos 会级联删除 todo_tags，
+-- 因此不声明外键，由写入方保证清单存在；清单只能归档、不能删除
+ALTER TABLE todos
+ADD COLUMN list_id INTEGER NOT NULL DEFAULT 1;
+
+CREATE INDEX todos_list_id ON todos (list_id, position);
@@ -0,0 +1,5 @@
+-- 子事项随父事项一起删除；默认值为 NULL，因此可以直接追加带外键的列
+ALTER TABLE todos
+ADD COLUMN parent_id INTEGER REFERENCES todos (id) ON DELETE CASCADE;
+
+CREATE INDEX todos_parent_id ON todos (parent_id);
@@ -0,0 +1,3 @@
+-- iCalendar RRULE，以 due_at 作为起始时间；完成后由下一次重复的事项接管
+ALTER TABLE todos
+ADD COLUMN recurrence TEXT;
@@ -0,0 +1,5 @@
+-- 软删除：deleted_at 不为空的事项位于回收站，超过保留期后由后台任务彻底删除
+ALTER TABLE todos
+ADD COLUMN deleted_at INTEGER;
+
+CREATE INDEX todos_deleted_at ON todos (deleted_at) WHERE deleted_at IS NOT NULL;
@@ -0,0 +1,12 @@
+-- 撤销/重做的操作日志，changes 为 JSON 数组，记录每个受影响事项在操作前后的状态
+-- undone 为 TRUE 的操作构成重做栈，记录新操作时被清除；AUTOINCREMENT 保证 id 不被复用，按 id 即可还原操作顺序
+CREATE TABLE operations
+(
+    id         INTEGER PRIMARY KEY AUTOINCREMENT,
+    kind       TEXT    NOT NULL,
+    changes    TEXT    NOT NULL,
+    undone     BOOLEAN NOT NULL DEFAULT 0,
+    created_at INTEGER NOT NULL DEFAULT (UNIXEPOCH(CURRENT_TIMESTAMP))
+);
+
+CREATE INDEX operations_undone ON operations (undone, id);
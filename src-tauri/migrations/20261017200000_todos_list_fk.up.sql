@@ -0,0 +1,79 @@
+-- 重建 todos，为 list_id 声明外键；清单只能归档、不能删除，因此不设 ON DELETE 动作
+-- 删除旧表时会级联删除 todo_tags 中的关联，先暂存到临时表，重建后再写回
+CREATE TEMPORARY TABLE todo_tags_backup AS
+SELECT todo_id, tag_id, created_at
+FROM todo_tags;
+
+-- parent_id 引用新表自身，以免删除旧表时级联删除已复制的子事项；重命名时 SQLite 会一并改为 todos
+CREATE TABLE todos_new
+(
+    id          INTEGER PRIMARY KEY NOT NULL,
+    description TEXT                NOT NULL,
+    done        BOOLEAN             NOT NULL DEFAULT 0,
+    created_at  INTEGER             NOT NULL DEFAULT (UNIXEPOCH(CURRENT_TIMESTAMP)),
+    updated_at  INTEGER             NOT NULL DEFAULT (UNIXEPOCH(CURRENT_TIMESTAMP)),
+    position    INTEGER             NOT NULL DEFAULT 0,
+    due_at      INTEGER,
+    remind_at   INTEGER,
+    reminded    BOOLEAN             NOT NULL DEFAULT 0,
+    list_id     INTEGER             NOT NULL DEFAULT 1 REFERENCES lists (id),
+    parent_id   INTEGER REFERENCES todos_new (id) ON DELETE CASCADE,
+    recurrence  TEXT,
+    deleted_at  INTEGER
+);
+
+-- 指向不存在清单的事项归入默认清单
+INSERT INTO todos_new (id, description, done, created_at, updated_at, position, due_at, remind_at, reminded,
+                       list_id, parent_id, recurrence, deleted_at)
+SELECT id,
+       description,
+       done,
+       created_at,
+       updated_at,
+       position,
+       due_at,
+       remind_at,
+       reminded,
+       CASE WHEN list_id IN (SELECT id FROM lists) THEN list_id ELSE 1 END,
+       parent_id,
+       recurrence,
+       deleted_at
+FROM todos;
+
+-- 旧表 parent_id 的自引用级联会让 DROP TABLE 报 table is locked，先断开
+UPDATE todos
+SET parent_id = NULL;
+
+DROP TABLE todos;
+
+ALTER TABLE todos_new
+RENAME TO todos;
+
+INSERT INTO todo_tags (todo_id, tag_id, created_at)
+SELECT todo_id, tag_id, created_at
+FROM todo_tags_backup;
+
+DROP TABLE todo_tags_backup;
+
+CREATE INDEX todos_position ON todos (position, id);
+CREATE INDEX todos_pending_reminders ON todos (remind_at) WHERE reminded = 0;
+CREATE INDEX todos_list_id ON todos (list_id, position);
+CREATE INDEX todos_parent_id ON todos (parent_id);
+CREATE INDEX todos_deleted_at ON todos (deleted_at) WHERE deleted_at IS NOT NULL;
+
+-- 触发器随旧表一起删除，全文索引以 rowid 关联，内容不变，无需重建
+CREATE TRIGGER todos_fts_after_insert AFTER INSERT ON todos
+BEGIN
+    INSERT INTO todos_fts (rowid, description) VALUES (NEW.id, NEW.description);
+END;
+
+CREATE TRIGGER todos_fts_after_delete AFTER DELETE ON todos
+BEGIN
+    INSERT INTO todos_fts (todos_fts, rowid, description) VALUES ('delete', OLD.id, OLD.description);
+END;
+
+CREATE TRIGGER todos_fts_after_update AFTER UPDATE OF description ON todos
+BEGIN
+    INSERT INTO todos_fts (todos_fts, rowid, description) VALUES ('delete', OLD.id, OLD.description);
+    INSERT INTO todos_fts (rowid, description) VALUES (NEW.id, NEW.description);
+END;
@@ -1,20 +1,99 @@
+use crate::graphql::persisted::PersistedBatchRequest;
 use crate::graphql::{self, scalar};
 use crate::state::AppState;
-use juniper::http::GraphQLRequest;
-use tauri::command;
+use juniper::futures::{stream, StreamExt};
+use juniper::http::{GraphQLRequest, GraphQLResponse};
+use juniper::{Object, Value};
+use tauri::{command, ipc::Channel};
 
+/// `body` 可以是单个请求，也可以是请求数组；传入数组时按相同顺序返回响应数组，
+/// 同一批次中的请求共享同一个 `Context`。请求支持 APQ，可以只传查询文本的 sha256
 #[command]
 pub async fn graphql(
     state: tauri::State<'_, AppState>,
-    body: GraphQLRequest<scalar::CustomScalarValue>,
+    body: PersistedBatchRequest,
 ) -> Result<serde_json::Value, serde_json::Value> {
     let pool = state.pool.clone();
-    let context = graphql::Context::new(pool);
+    let context = graphql::Context::new(pool, state.events.clone());
 
-    let response = body.execute(&state.schema, &context).await;
+    let response = state
+        .persisted_queries
+        .execute_batch(&body, &state.limits, &state.schema, &context)
+        .await;
     match (response.is_ok(), serde_json::to_value(response)) {
         (true, Ok(v)) => Ok(v),
         (false, Ok(v)) => Err(v),
         (_, Err(e)) => Err(serde_json::Value::String(e.to_string())),
     }
 }
+
+/// 启动一个订阅，之后每条结果都以 GraphQL 响应的形式通过 `on_event` 推送给调用方窗口，
+/// 返回值为订阅 ID，用于 `graphql_unsubscribe`
+#[command]
+pub async fn graphql_subscribe(
+    state: tauri::State<'_, AppState>,
+    body: GraphQLRequest<scalar::CustomScalarValue>,
+    on_event: Channel<serde_json::Value>,
+) -> Result<u32, serde_json::Value> {
+    if !state.persisted_queries.is_allowed(&body.query) {
+        return Err(serde_json::json!({
+            "message": "Query is not registered in the persisted query manifest",
+            "extensions": { "code": "PERSISTED_QUERY_NOT_ALLOWED" },
+        }));
+    }
+    if let Err(e) = state.limits.check_query(
+        &state.schema,
+        &body.query,
+        body.operation_name.as_deref(),
+        &body.variables(),
+    ) {
+        return Err(serde_json::to_value(GraphQLResponse::error(e))
+            .unwrap_or_else(|e| serde_json::Value::String(e.to_string())));
+    }
+    let schema = state.schema.clone();
+    // 整个订阅共用一个 Context，加载器的缓存在每条事件推送时清空，见 `Context::subscribe`
+    let context = graphql::Context::new(state.pool.clone(), state.events.clone());
+
+    let task = tauri::async_runtime::spawn(async move {
+        let send = |response: GraphQLResponse<scalar::CustomScalarValue>| {
+            serde_json::to_value(response)
+                .map_err(|e| e.to_string())
+                .and_then(|v| on_event.send(v).map_err(|e| e.to_string()))
+        };
+        let fields = match juniper::http::resolve_into_stream(&body, &schema, &context).await {
+            Ok((Value::Object(fields), errors)) if errors.is_empty() => fields,
+            Ok((_, errors)) => {
+                let _ = send(GraphQLResponse::from_result(Ok((Value::null(), errors))));
+                return;
+            }
+            Err(e) => {
+                let _ = send(GraphQLResponse::from_result(Err(e)));
+                return;
+            }
+        };
+        // 每个根字段都是一个独立的流，合并后按到达顺序推送
+        let mut responses = stream::select_all(fields.into_iter().filter_map(|(name, value)| {
+            let Value::Scalar(values) = value else {
+                return None;
+            };
+            Some(values.map(move |value| {
+                GraphQLResponse::from_result(Ok(match value {
+                    Ok(value) => (Object::from_iter([(name.clone(), value)]).into(), vec![]),
+                    Err(e) => (Value::null(), vec![e]),
+                }))
+            }))
+        }));
+        while let Some(response) = responses.next().await {
+            // 窗口已关闭或 Channel 已失效，结束订阅
+            if send(response).is_err() {
+                break;
+            }
+        }
+    });
+    Ok(state.subscriptions.insert(task))
+}
+
+#[command]
+pub fn graphql_unsubscribe(state: tauri::State<'_, AppState>, id: u32) -> bool {
+    state.subscriptions.remove(id)
+}
@@ -0,0 +1,37 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use sqlx::sqlite::SqliteConnectOptions;
+use sqlx::SqlitePool;
+use tauri::{AppHandle, Manager};
+
+/// 数据库文件名，位于应用数据目录下
+const DATABASE_FILE: &str = "todos.db";
+
+/// 应用数据目录，不存在时自动创建；数据库和游标密钥都保存在这里
+pub fn data_dir(app: &AppHandle) -> anyhow::Result<PathBuf> {
+    let dir = app
+        .path()
+        .app_data_dir()
+        .context("Failed to resolve the app data directory")?;
+    std::fs::create_dir_all(&dir)
+        .with_context(|| format!("Failed to create the app data directory {}", dir.display()))?;
+    Ok(dir)
+}
+
+/// 打开 `dir` 下的数据库（不存在时自动创建），并执行内嵌的迁移
+pub async fn connect(dir: &Path) -> anyhow::Result<SqlitePool> {
+    let path = dir.join(DATABASE_FILE);
+    let options = SqliteConnectOptions::new()
+        .filename(&path)
+        .create_if_missing(true);
+    let pool = SqlitePool::connect_with(options)
+        .await
+        .with_context(|| format!("Failed to open the database {}", path.display()))?;
+    // 迁移在编译期嵌入二进制，安装后的应用不依赖 migrations 目录和 sqlx-cli
+    sqlx::migrate!("./migrations")
+        .run(&pool)
+        .await
+        .with_context(|| format!("Failed to apply migrations to {}", path.display()))?;
+    Ok(pool)
+}
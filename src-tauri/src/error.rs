@@ -0,0 +1,128 @@
+use std::fmt;
+use std::sync::Arc;
+
+use juniper::{graphql_value, FieldError, IntoFieldError, ScalarValue};
+use sqlx::error::{DatabaseError, ErrorKind};
+
+use crate::graphql::{relay, scalar};
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// 仓储层的领域错误，转换为 GraphQL 错误时以 `extensions.code` 区分类型
+///
+/// 可以克隆，批量加载失败时同一批次的所有调用方都会收到这个错误
+#[derive(Debug, Clone)]
+pub enum Error {
+    /// 要操作的对象不存在
+    NotFound(String),
+    /// 输入不满足约束（包括数据库的 NOT NULL、CHECK 约束）
+    Validation(String),
+    /// 与已有数据冲突（唯一约束、外键约束）
+    Conflict(String),
+    /// 数据库暂时不可用，例如连接池超时、数据库被锁定，客户端可以稍后重试
+    StorageUnavailable(Arc<sqlx::Error>),
+    /// 其他未预期的错误
+    Internal(Arc<anyhow::Error>),
+}
+
+impl Error {
+    pub fn not_found(type_name: &str, id: scalar::ID) -> Self {
+        let id = relay::GlobalId::new(type_name, id);
+        Self::NotFound(format!("{type_name} '{}' not found", String::from(&id)))
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::NotFound(_) => "NOT_FOUND",
+            Self::Validation(_) => "VALIDATION_FAILED",
+            Self::Conflict(_) => "CONFLICT",
+            Self::StorageUnavailable(_) => "STORAGE_UNAVAILABLE",
+            Self::Internal(_) => "INTERNAL",
+        }
+    }
+
+    /// 可以直接展示给客户端的消息，release 构建下隐藏数据库和内部错误的细节
+    fn public_message(&self) -> String {
+        match self {
+            Self::StorageUnavailable(_) if !cfg!(debug_assertions) => {
+                "Storage is temporarily unavailable".to_string()
+            }
+            Self::Internal(_) if !cfg!(debug_assertions) => "Internal server error".to_string(),
+            _ => self.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotFound(msg) | Self::Validation(msg) | Self::Conflict(msg) => f.write_str(msg),
+            Self::StorageUnavailable(e) => write!(f, "Storage is temporarily unavailable: {e}"),
+            Self::Internal(e) => write!(f, "Internal server error: {e:#}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::StorageUnavailable(e) => Some(e.as_ref()),
+            Self::Internal(e) => Some(e.as_ref().as_ref()),
+            _ => None,
+        }
+    }
+}
+
+impl From<sqlx::Error> for Error {
+    fn from(e: sqlx::Error) -> Self {
+        match e {
+            sqlx::Error::RowNotFound => Self::NotFound("Record not found".to_string()),
+            sqlx::Error::Database(db) if is_busy(db.as_ref()) => {
+                Self::StorageUnavailable(Arc::new(sqlx::Error::Database(db)))
+            }
+            sqlx::Error::Database(db) => match db.kind() {
+                ErrorKind::UniqueViolation | ErrorKind::ForeignKeyViolation => {
+                    Self::Conflict(db.message().to_string())
+                }
+                ErrorKind::NotNullViolation | ErrorKind::CheckViolation => {
+                    Self::Validation(db.message().to_string())
+                }
+                _ => Self::Internal(Arc::new(sqlx::Error::Database(db).into())),
+            },
+            sqlx::Error::PoolTimedOut
+            | sqlx::Error::PoolClosed
+            | sqlx::Error::Io(_)
+            | sqlx::Error::WorkerCrashed => Self::StorageUnavailable(Arc::new(e)),
+            e => Self::Internal(Arc::new(e.into())),
+        }
+    }
+}
+
+/// SQLITE_BUSY 或 SQLITE_LOCKED，扩展错误码的低 8 位为主错误码
+fn is_busy(db: &dyn DatabaseError) -> bool {
+    db.code()
+        .and_then(|code| code.parse::<i32>().ok())
+        .is_some_and(|code| matches!(code & 0xff, 5 | 6))
+}
+
+impl From<fmt::Error> for Error {
+    fn from(e: fmt::Error) -> Self {
+        Self::Internal(Arc::new(e.into()))
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(e: serde_json::Error) -> Self {
+        Self::Internal(Arc::new(e.into()))
+    }
+}
+
+impl<S: ScalarValue> IntoFieldError<S> for Error {
+    fn into_field_error(self) -> FieldError<S> {
+        if matches!(self, Self::StorageUnavailable(_) | Self::Internal(_)) {
+            eprintln!("Error: {self:?}");
+        }
+        let code = self.code();
+        FieldError::new(self.public_message(), graphql_value!({ "code": code }))
+    }
+}
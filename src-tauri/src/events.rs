@@ -0,0 +1,60 @@
+use juniper::futures::{stream, Stream};
+use tokio::sync::broadcast;
+
+use crate::{graphql::scalar, models::todo::Todo};
+
+/// 待办事项的变更事件，由 `TodoRepository` 在写操作成功后发布
+#[derive(Debug, Clone)]
+pub enum TodoEvent {
+    Added(Todo),
+    Updated(Todo),
+    Removed(scalar::ID),
+    Cleared(Vec<scalar::ID>),
+}
+
+/// 进程内的事件广播中心，所有窗口的订阅都从这里接收变更
+#[derive(Debug, Clone)]
+pub struct EventHub {
+    sender: broadcast::Sender<TodoEvent>,
+}
+
+impl EventHub {
+    pub fn new(capacity: usize) -> Self {
+        let (sender, _) = broadcast::channel(capacity);
+        Self { sender }
+    }
+
+    pub fn publish(&self, event: TodoEvent) {
+        // 没有任何订阅者时 send 会返回错误，这不影响写操作本身
+        let _ = self.sender.send(event);
+    }
+
+    /// 订阅事件流，`filter` 返回 `None` 的事件会被跳过
+    pub fn subscribe<T, F>(&self, filter: F) -> impl Stream<Item = T> + Send + 'static
+    where
+        T: Send + 'static,
+        F: Fn(TodoEvent) -> Option<T> + Send + 'static,
+    {
+        let receiver = self.sender.subscribe();
+        stream::unfold((receiver, filter), |(mut receiver, filter)| async move {
+            loop {
+                match receiver.recv().await {
+                    Ok(event) => {
+                        if let Some(item) = filter(event) {
+                            return Some((item, (receiver, filter)));
+                        }
+                    }
+                    // 消费过慢导致部分事件被覆盖，跳过即可，客户端可以通过查询重新同步
+                    Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                    Err(broadcast::error::RecvError::Closed) => return None,
+                }
+            }
+        })
+    }
+}
+
+impl Default for EventHub {
+    fn default() -> Self {
+        Self::new(64)
+    }
+}
@@ -1,17 +1,67 @@
+use juniper::futures::Stream;
 use sqlx::SqlitePool;
 
-use crate::repositories::TodoRepository;
+use crate::events::{EventHub, TodoEvent};
+use crate::graphql::{
+    loader::{CacheScope, DataLoader},
+    scalar,
+};
+use crate::models::{list::TodoList, operation::Operation, tag::Tag, todo::Todo};
+use crate::repositories::{ListRepository, OperationRepository, TagRepository, TodoRepository};
 
 pub struct Context {
     pub todo_repo: TodoRepository,
+    /// 按 ID 加载待办事项，同一请求中的查询会合并并缓存
+    pub todo_loader: DataLoader<scalar::ID, Todo, TodoRepository>,
+    /// 按事项 ID 加载子孙事项数
+    pub descendant_count_loader: DataLoader<scalar::ID, i32, TodoRepository>,
+    pub tag_repo: TagRepository,
+    pub tag_loader: DataLoader<scalar::ID, Tag, TagRepository>,
+    pub list_repo: ListRepository,
+    pub list_loader: DataLoader<scalar::ID, TodoList, ListRepository>,
+    pub operation_repo: OperationRepository,
+    pub operation_loader: DataLoader<scalar::ID, Operation, OperationRepository>,
+    pub events: EventHub,
+    /// 所有加载器共享的缓存范围
+    cache_scope: CacheScope,
 }
 
 impl Context {
-    pub fn new(pool: SqlitePool) -> Self {
+    pub fn new(pool: SqlitePool, events: EventHub) -> Self {
+        let todo_repo = TodoRepository::new(pool.clone(), events.clone());
+        let tag_repo = TagRepository::new(pool.clone());
+        let list_repo = ListRepository::new(pool.clone());
+        let operation_repo = OperationRepository::new(pool);
+        let scope = CacheScope::default();
         Self {
-          todo_repo: TodoRepository::new(pool),
+            todo_loader: DataLoader::new(todo_repo.clone(), scope.clone()),
+            descendant_count_loader: DataLoader::new(todo_repo.clone(), scope.clone()),
+            todo_repo,
+            tag_loader: DataLoader::new(tag_repo.clone(), scope.clone()),
+            tag_repo,
+            list_loader: DataLoader::new(list_repo.clone(), scope.clone()),
+            list_repo,
+            operation_loader: DataLoader::new(operation_repo.clone(), scope.clone()),
+            operation_repo,
+            events,
+            cache_scope: scope,
         }
     }
+
+    /// 订阅事件流，与 `EventHub::subscribe` 相同，但每条推送的事件都在新的缓存范围中解析，
+    /// 避免长时间运行的订阅读到旧数据，缓存也不会无限增长
+    pub fn subscribe<T, F>(&self, filter: F) -> impl Stream<Item = T> + Send + 'static
+    where
+        T: Send + 'static,
+        F: Fn(TodoEvent) -> Option<T> + Send + 'static,
+    {
+        let scope = self.cache_scope.clone();
+        self.events.subscribe(move |event| {
+            let item = filter(event)?;
+            scope.renew();
+            Some(item)
+        })
+    }
 }
 
 impl juniper::Context for Context {}
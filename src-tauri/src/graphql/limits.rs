@@ -0,0 +1,340 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+use juniper::{
+    executor::get_operation,
+    graphql_value,
+    parser::parse_document_source,
+    validation::{visit_all_rules, ValidatorContext},
+    Definition, Document, FieldError, InputValue, Operation, ScalarValue, Selection, Variables,
+};
+
+use super::{scalar::CustomScalarValue, Schema};
+
+/// 未传 `first`/`last` 时连接默认返回的条数，与 `Pagination::limit` 保持一致
+const DEFAULT_PAGE_SIZE: i32 = 10;
+
+/// 查询的深度、代价和分页大小上限，在执行前检查
+///
+/// 代价的计算方式：每个字段计 1，`__typename` 不计；连接字段下的 `edges`、`nodes`
+/// 按 `first`/`last` 或页码分页的 `pageSize`（未传时为默认页大小）倍数计算，嵌套的连接会逐层相乘；
+/// 按 `ids` 批量获取的字段（例如 `nodes(ids: [...])`）的子字段按 `ids` 的个数倍数计算
+#[derive(Debug, Clone)]
+pub struct QueryLimits {
+    pub max_depth: usize,
+    pub max_cost: u64,
+    pub max_page_size: i32,
+}
+
+impl Default for QueryLimits {
+    fn default() -> Self {
+        Self {
+            max_depth: 15,
+            max_cost: 10_000,
+            max_page_size: 1000,
+        }
+    }
+}
+
+impl QueryLimits {
+    pub fn check<S: ScalarValue>(
+        &self,
+        document: &Document<S>,
+        operation: &Operation<S>,
+        variables: &Variables<S>,
+    ) -> Result<(), FieldError<S>> {
+        let fragments = document
+            .iter()
+            .filter_map(|def| match def {
+                Definition::Fragment(f) => Some((f.item.name.item, &f.item.selection_set[..])),
+                Definition::Operation(_) => None,
+            })
+            .collect::<HashMap<_, _>>();
+        let analyzer = Analyzer {
+            limits: self,
+            fragments,
+            variables,
+            expanding: RefCell::default(),
+        };
+        let (depth, cost) = analyzer.analyze(&operation.selection_set, 1)?;
+        if depth > self.max_depth {
+            return Err(FieldError::new(
+                format!(
+                    "Query depth {depth} exceeds the limit of {}",
+                    self.max_depth
+                ),
+                graphql_value!({
+                    "code": "QUERY_TOO_DEEP",
+                    "depth": (depth as i32),
+                    "max": (self.max_depth as i32),
+                }),
+            ));
+        }
+        if cost > self.max_cost {
+            return Err(FieldError::new(
+                format!("Query cost {cost} exceeds the limit of {}", self.max_cost),
+                graphql_value!({
+                    "code": "QUERY_TOO_COMPLEX",
+                    "cost": (cost.min(i32::MAX as u64) as i32),
+                    "max": (self.max_cost.min(i32::MAX as u64) as i32),
+                }),
+            ));
+        }
+        Ok(())
+    }
+
+    /// 解析并校验查询文本后检查，用于不经过 `PersistedQueries` 的订阅；
+    /// 无法解析或校验失败的查询交给执行阶段报告错误
+    pub fn check_query(
+        &self,
+        schema: &Schema,
+        query: &str,
+        operation_name: Option<&str>,
+        variables: &Variables<CustomScalarValue>,
+    ) -> Result<(), FieldError<CustomScalarValue>> {
+        let Ok(document) = parse_document_source(query, &schema.schema) else {
+            return Ok(());
+        };
+        // 未经校验的文档可能包含循环引用的片段，分析时会无限展开
+        let mut ctx = ValidatorContext::new(&schema.schema, &document);
+        visit_all_rules(&mut ctx, &document);
+        if !ctx.into_errors().is_empty() {
+            return Ok(());
+        }
+        let Ok(operation) = get_operation(&document, operation_name) else {
+            return Ok(());
+        };
+        self.check(&document, &operation.item, variables)
+    }
+}
+
+struct Analyzer<'a, 'd, S> {
+    limits: &'a QueryLimits,
+    fragments: HashMap<&'d str, &'a [Selection<'d, S>]>,
+    variables: &'a Variables<S>,
+    /// 正在展开的片段，用于发现循环引用
+    expanding: RefCell<Vec<&'d str>>,
+}
+
+impl<'d, S: ScalarValue> Analyzer<'_, 'd, S> {
+    /// 变量替换为传入的值
+    fn resolve<'v>(&'v self, value: &'v InputValue<S>) -> Option<&'v InputValue<S>> {
+        match value {
+            InputValue::Variable(var) => self.variables.get(var),
+            value => Some(value),
+        }
+    }
+
+    /// 返回选择集的 `(最大深度, 代价)`，`depth` 为选择集中字段所在的层级
+    fn analyze(
+        &self,
+        selections: &[Selection<'d, S>],
+        depth: usize,
+    ) -> Result<(usize, u64), FieldError<S>> {
+        let mut max_depth = 0;
+        let mut cost = 0u64;
+        for selection in selections {
+            let (d, c) = match selection {
+                Selection::Field(field) => {
+                    let field = &field.item;
+                    if field.name.item == "__typename" {
+                        continue;
+                    }
+                    let int_argument = |name: &str| {
+                        let (_, value) = field
+                            .arguments
+                            .as_ref()?
+                            .item
+                            .items
+                            .iter()
+                            .find(|(key, _)| key.item == name)?;
+                        match &value.item {
+                            InputValue::Variable(var) => self.variables.get(var)?.as_int_value(),
+                            value => value.as_int_value(),
+                        }
+                    };
+                    // 页码分页的每页条数位于输入对象中，例如 `pagination: { page: 1, pageSize: 20 }`
+                    let nested_int_argument = |name: &str| {
+                        field
+                            .arguments
+                            .as_ref()?
+                            .item
+                            .items
+                            .iter()
+                            .find_map(|(_, value)| {
+                                let object = self.resolve(&value.item)?.to_object_value()?;
+                                self.resolve(object.get(name)?)?.as_int_value()
+                            })
+                    };
+                    let page_size = int_argument("first")
+                        .or_else(|| int_argument("last"))
+                        .or_else(|| nested_int_argument("pageSize"));
+                    if let Some(size) = page_size.filter(|it| *it > self.limits.max_page_size) {
+                        return Err(FieldError::new(
+                            format!(
+                                "Page size {size} of '{}' exceeds the limit of {}",
+                                field.name.item, self.limits.max_page_size
+                            ),
+                            graphql_value!({
+                                "code": "VALUE_OUT_OF_RANGE",
+                                "min": 0,
+                                "max": (self.limits.max_page_size),
+                            }),
+                        ));
+                    }
+                    let page_size = page_size.unwrap_or(DEFAULT_PAGE_SIZE).max(0) as u64;
+                    let ids = field.arguments.as_ref().and_then(|arguments| {
+                        let (_, value) = arguments
+                            .item
+                            .items
+                            .iter()
+                            .find(|(key, _)| key.item == "ids")?;
+                        match self.resolve(&value.item)? {
+                            InputValue::List(items) => Some(items.len() as u64),
+                            // 列表参数可以直接传单个值
+                            InputValue::Null => Some(0),
+                            _ => Some(1),
+                        }
+                    });
+                    let mut max_child_depth = depth;
+                    let mut children_cost = 0u64;
+                    for child in field.selection_set.iter().flatten() {
+                        let (d, c) = self.analyze(std::slice::from_ref(child), depth + 1)?;
+                        let is_page = matches!(
+                            child,
+                            Selection::Field(f) if matches!(f.item.name.item, "edges" | "nodes")
+                        );
+                        let c = if is_page {
+                            c.saturating_mul(page_size)
+                        } else {
+                            c
+                        };
+                        max_child_depth = max_child_depth.max(d);
+                        children_cost = children_cost.saturating_add(c);
+                    }
+                    if let Some(ids) = ids {
+                        children_cost = children_cost.saturating_mul(ids);
+                    }
+                    (max_child_depth, children_cost.saturating_add(1))
+                }
+                Selection::FragmentSpread(spread) => {
+                    let name = spread.item.name.item;
+                    let Some(selections) = self.fragments.get(name) else {
+                        continue;
+                    };
+                    if self.expanding.borrow().contains(&name) {
+                        return Err(FieldError::new(
+                            format!("Fragment '{name}' spreads itself"),
+                            graphql_value!({ "code": "FRAGMENT_CYCLE" }),
+                        ));
+                    }
+                    self.expanding.borrow_mut().push(name);
+                    let result = self.analyze(selections, depth);
+                    self.expanding.borrow_mut().pop();
+                    result?
+                }
+                Selection::InlineFragment(fragment) => {
+                    self.analyze(&fragment.item.selection_set, depth)?
+                }
+            };
+            max_depth = max_depth.max(d);
+            cost = cost.saturating_add(c);
+        }
+        Ok((max_depth, cost))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graphql::create_schema;
+
+    const LIMITS: QueryLimits = QueryLimits {
+        max_depth: 5,
+        max_cost: 500,
+        max_page_size: 100,
+    };
+
+    /// 不经过校验直接分析，返回错误码
+    fn check(query: &str) -> Result<(), String> {
+        let schema = create_schema();
+        let document = parse_document_source(query, &schema.schema).unwrap();
+        let operation = get_operation(&document, None).unwrap();
+        LIMITS
+            .check(&document, &operation.item, &Variables::new())
+            .map_err(|e| {
+                let code = e
+                    .extensions()
+                    .as_object_value()
+                    .unwrap()
+                    .get_field_value("code");
+                code.unwrap().as_string_value().unwrap().to_owned()
+            })
+    }
+
+    #[test]
+    fn cost_is_weighted_by_page_size() {
+        let query =
+            "{ listTodos(first: 10) { edges { node { id description } cursor } totalCount } }";
+        assert_eq!(check(query), Ok(()));
+        let query = "{ listTodos(first: 100) { edges { node { id description done } cursor } totalCount } }";
+        assert_eq!(check(query), Err("QUERY_TOO_COMPLEX".into()));
+        // 未传 first 时按默认页大小计算
+        let query = "{ listTodos { nodes { children { nodes { id description done createdAt updatedAt } } } } }";
+        assert_eq!(check(query), Err("QUERY_TOO_COMPLEX".into()));
+    }
+
+    #[test]
+    fn cost_is_weighted_by_ids() {
+        let ids = |n: usize| {
+            let ids = vec!["\"VG9kbzox\""; n].join(", ");
+            format!("{{ nodes(ids: [{ids}]) {{ id ... on Todo {{ description done }} }} }}")
+        };
+        assert_eq!(check(&ids(100)), Ok(()));
+        assert_eq!(check(&ids(200)), Err("QUERY_TOO_COMPLEX".into()));
+    }
+
+    #[test]
+    fn rejects_large_pages_and_deep_queries() {
+        assert_eq!(
+            check("{ listTodos(last: 101) { totalCount } }"),
+            Err("VALUE_OUT_OF_RANGE".into())
+        );
+        assert_eq!(
+            check("{ listTodosPage(pagination: { page: 1, pageSize: 101 }) { totalCount } }"),
+            Err("VALUE_OUT_OF_RANGE".into())
+        );
+        let query = "{ listTodos(first: 1) { nodes { parent { parent { parent { id } } } } } }";
+        assert_eq!(check(query), Err("QUERY_TOO_DEEP".into()));
+    }
+
+    #[test]
+    fn fragments_count_towards_depth() {
+        let query = "
+            fragment P on Todo { parent { parent { id } } }
+            { listTodos(first: 1) { nodes { ...P } } }
+        ";
+        assert_eq!(check(query), Ok(()));
+        let query = "
+            fragment P on Todo { parent { parent { parent { id } } } }
+            { listTodos(first: 1) { nodes { ... on Todo { ...P } } } }
+        ";
+        assert_eq!(check(query), Err("QUERY_TOO_DEEP".into()));
+    }
+
+    #[test]
+    fn rejects_fragment_cycles() {
+        let query = "
+            fragment A on Todo { parent { ...B } }
+            fragment B on Todo { parent { ...A } }
+            { listTodos(first: 1) { nodes { ...A } } }
+        ";
+        assert_eq!(check(query), Err("FRAGMENT_CYCLE".into()));
+        // 订阅路径先校验，循环引用留给执行阶段报告
+        let schema = create_schema();
+        let query = "fragment F on Todo { parent { ...F } } subscription { todoAdded { ...F } }";
+        assert!(LIMITS
+            .check_query(&schema, query, None, &Variables::new())
+            .is_ok());
+    }
+}
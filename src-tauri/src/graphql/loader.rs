@@ -0,0 +1,238 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::hash::Hash;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use anyhow::anyhow;
+use tokio::sync::watch;
+
+use crate::error::{Error, Result};
+
+/// 按键批量加载数据，返回结果中缺少的键视为不存在
+pub trait BatchFn<K, V>: Send + Sync {
+    fn load(&self, keys: &[K]) -> impl Future<Output = Result<HashMap<K, V>>> + Send;
+}
+
+/// 批次加载的结果，缓存可能在批次进行中因范围更新而清空，因此等待方直接从这里取值
+type BatchResult<K, V> = Option<Result<Arc<HashMap<K, V>>>>;
+
+/// 加载器缓存的有效范围，同一个 `Context` 中的加载器共享
+///
+/// 普通请求只有一个范围；订阅在整个生命周期内使用同一个 `Context`，每推送一条事件就开启新的范围，
+/// 加载器随之丢弃之前的缓存，事件中嵌套的字段与新请求一样重新查询
+#[derive(Debug, Clone, Default)]
+pub struct CacheScope(Arc<AtomicU64>);
+
+impl CacheScope {
+    pub fn renew(&self) {
+        self.0.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn current(&self) -> u64 {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// 发出查询的调用持有的批次，调用在完成前被取消（例如同级字段出错、`select!`、超时）时，
+/// 把批次的键放回 `pending` 并丢弃发送端，等待该批次的调用会重新加入下一个批次
+struct Leader<'a, K: Eq, V> {
+    state: &'a Mutex<LoaderState<K, V>>,
+    sender: Option<watch::Sender<BatchResult<K, V>>>,
+    /// 已从 `pending` 取出、尚未写入缓存的键
+    keys: Vec<K>,
+}
+
+impl<K: Eq, V> Leader<'_, K, V> {
+    fn finish(mut self, result: Result<Arc<HashMap<K, V>>>) {
+        if let Some(sender) = self.sender.take() {
+            sender.send_replace(Some(result));
+        }
+    }
+}
+
+impl<K: Eq, V> Drop for Leader<'_, K, V> {
+    fn drop(&mut self) {
+        let Some(sender) = self.sender.take() else {
+            return;
+        };
+        if let Ok(mut state) = self.state.lock() {
+            if state
+                .batch
+                .as_ref()
+                .is_some_and(|it| it.same_channel(&sender.subscribe()))
+            {
+                state.batch = None;
+            }
+            for key in self.keys.drain(..) {
+                if !state.pending.contains(&key) {
+                    state.pending.push(key);
+                }
+            }
+        }
+    }
+}
+
+/// 请求级的批量加载器
+///
+/// 同一轮执行中（例如列表中每一项的同名字段）发起的 `load` 会合并为一次 `BatchFn::load`，
+/// 结果缓存到当前 `CacheScope` 结束，同一个键在一次请求中最多查询一次
+pub struct DataLoader<K, V, F> {
+    batch_fn: F,
+    scope: CacheScope,
+    state: Mutex<LoaderState<K, V>>,
+}
+
+struct LoaderState<K, V> {
+    /// `cache` 所属的范围
+    scope: u64,
+    cache: HashMap<K, Option<V>>,
+    /// 正在收集、尚未发出的批次
+    pending: Vec<K>,
+    batch: Option<watch::Receiver<BatchResult<K, V>>>,
+}
+
+impl<K, V, F> DataLoader<K, V, F>
+where
+    K: Eq + Hash + Clone + Send + Sync,
+    V: Clone + Send + Sync,
+    F: BatchFn<K, V>,
+{
+    pub fn new(batch_fn: F, scope: CacheScope) -> Self {
+        Self {
+            batch_fn,
+            state: Mutex::new(LoaderState {
+                scope: scope.current(),
+                cache: HashMap::new(),
+                pending: Vec::new(),
+                batch: None,
+            }),
+            scope,
+        }
+    }
+
+    /// 锁定状态，范围已更新时先清空缓存
+    fn state(&self) -> std::sync::MutexGuard<'_, LoaderState<K, V>> {
+        let mut state = self.state.lock().unwrap();
+        let scope = self.scope.current();
+        if state.scope != scope {
+            state.scope = scope;
+            state.cache.clear();
+        }
+        state
+    }
+
+    pub async fn load(&self, key: K) -> Result<Option<V>> {
+        loop {
+            let (mut receiver, sender) = {
+                let mut state = self.state();
+                if let Some(value) = state.cache.get(&key) {
+                    return Ok(value.clone());
+                }
+                if !state.pending.contains(&key) {
+                    state.pending.push(key.clone());
+                }
+                match state.batch.as_ref() {
+                    Some(receiver) => (receiver.clone(), None),
+                    None => {
+                        let (sender, receiver) = watch::channel(None);
+                        state.batch = Some(receiver.clone());
+                        (receiver, Some(sender))
+                    }
+                }
+            };
+            // 第一个加入批次的调用负责发出查询
+            if let Some(sender) = sender {
+                let mut leader = Leader {
+                    state: &self.state,
+                    sender: Some(sender),
+                    keys: Vec::new(),
+                };
+                // 让出执行权，同一轮中其他字段的 load 会在此期间加入本批次
+                tokio::task::yield_now().await;
+                leader.keys = {
+                    let mut state = self.state();
+                    state.batch = None;
+                    std::mem::take(&mut state.pending)
+                };
+                let result = self.batch_fn.load(&leader.keys).await.map(|values| {
+                    let mut state = self.state();
+                    for key in std::mem::take(&mut leader.keys) {
+                        let value = values.get(&key).cloned();
+                        state.cache.insert(key, value);
+                    }
+                    Arc::new(values)
+                });
+                leader.finish(result);
+            }
+            // 发出查询的调用被取消时批次作废，重新加入下一个批次
+            let Ok(result) = receiver.wait_for(Option::is_some).await else {
+                continue;
+            };
+            let result = result.clone();
+            let values = result.unwrap_or_else(|| Ok(Arc::default()))?;
+            // 批次进行中 `prime` 写入的值更新
+            if let Some(value) = self.state().cache.get(&key) {
+                return Ok(value.clone());
+            }
+            return Ok(values.get(&key).cloned());
+        }
+    }
+
+    /// 写操作之后更新缓存，避免同一请求中后续的读取拿到旧数据
+    pub fn prime(&self, key: K, value: V) {
+        self.state().cache.insert(key, Some(value));
+    }
+
+    pub fn clear(&self, key: &K) {
+        self.state().cache.remove(key);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::pin::pin;
+    use std::sync::atomic::AtomicUsize;
+
+    use juniper::futures::FutureExt;
+
+    use super::*;
+
+    #[derive(Default)]
+    struct Squares(AtomicUsize);
+
+    impl BatchFn<i64, i64> for Squares {
+        async fn load(&self, keys: &[i64]) -> Result<HashMap<i64, i64>> {
+            self.0.fetch_add(1, Ordering::Relaxed);
+            Ok(keys.iter().map(|it| (*it, it * it)).collect())
+        }
+    }
+
+    #[tokio::test]
+    async fn batches_loads() {
+        let loader = DataLoader::new(Squares::default(), CacheScope::default());
+        let (a, b) = tokio::join!(loader.load(2), loader.load(3));
+        assert_eq!((a.unwrap(), b.unwrap()), (Some(4), Some(9)));
+        assert_eq!(loader.load(2).await.unwrap(), Some(4));
+        assert_eq!(loader.batch_fn.0.load(Ordering::Relaxed), 1);
+    }
+
+    #[tokio::test]
+    async fn survives_cancelled_leader() {
+        let loader = DataLoader::new(Squares::default(), CacheScope::default());
+        // 发出查询的调用在让出执行权时被丢弃
+        assert!(loader.load(2).now_or_never().is_none());
+        assert_eq!(loader.load(3).await.unwrap(), Some(9));
+        assert_eq!(loader.load(2).await.unwrap(), Some(4));
+
+        // 等待同一批次的调用在发出查询的调用被丢弃后重新加入批次
+        let mut follower = pin!(loader.load(5));
+        {
+            let mut leader = pin!(loader.load(4));
+            assert!(leader.as_mut().now_or_never().is_none());
+            assert!(follower.as_mut().now_or_never().is_none());
+        }
+        assert_eq!(follower.await.unwrap(), Some(25));
+        assert_eq!(loader.load(4).await.unwrap(), Some(16));
+    }
+}
@@ -1,4 +1,7 @@
 pub mod context;
+pub mod limits;
+pub mod loader;
+pub mod persisted;
 pub mod relay;
 pub mod scalar;
 pub mod schema;
@@ -0,0 +1,304 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+
+use juniper::{
+    executor::{execute_validated_query_async, get_operation},
+    graphql_value,
+    http::{GraphQLBatchResponse, GraphQLResponse},
+    parser::parse_document_source,
+    validation::{validate_input_values, visit_all_rules, ValidatorContext},
+    Definition, Document, ExecutionError, FieldError, GraphQLError, InputValue, Value, Variables,
+};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use yoke::{Yoke, Yokeable};
+
+use super::{limits::QueryLimits, scalar::CustomScalarValue, Context, Schema};
+
+/// 构建时登记的查询清单（sha256 -> 查询文本），由 `pnpm persisted-queries` 从前端源码中提取
+const MANIFEST: &str = include_str!("../../persisted-queries.json");
+
+/// 兼容 APQ（Automatic Persisted Queries）协议的请求，`query` 可以省略，只传查询文本的 sha256
+#[derive(Debug, Deserialize)]
+pub struct PersistedRequest {
+    pub query: Option<String>,
+    #[serde(rename = "operationName")]
+    pub operation_name: Option<String>,
+    pub variables: Option<InputValue<CustomScalarValue>>,
+    pub extensions: Option<RequestExtensions>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RequestExtensions {
+    #[serde(rename = "persistedQuery")]
+    pub persisted_query: Option<PersistedQuery>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PersistedQuery {
+    pub version: i32,
+    #[serde(rename = "sha256Hash")]
+    pub sha256_hash: String,
+}
+
+/// 单个请求或请求数组，与 `GraphQLBatchRequest` 相同
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum PersistedBatchRequest {
+    Single(PersistedRequest),
+    Batch(Vec<PersistedRequest>),
+}
+
+impl PersistedRequest {
+    fn variables(&self) -> Variables<CustomScalarValue> {
+        self.variables
+            .as_ref()
+            .and_then(|iv| {
+                iv.to_object_value()
+                    .map(|o| o.into_iter().map(|(k, v)| (k.into(), v.clone())).collect())
+            })
+            .unwrap_or_default()
+    }
+}
+
+/// 解析后的文档，其中的名称等借用查询文本
+#[derive(Yokeable)]
+struct ParsedDocument<'a>(Vec<Definition<'a, CustomScalarValue>>);
+
+/// 已通过校验的文档与它借用的查询文本
+type ValidatedDocument = Arc<Yoke<ParsedDocument<'static>, Arc<str>>>;
+
+/// 已解析并通过校验的查询，按 sha256 索引；命中缓存的查询直接执行，不再重复解析和校验
+pub struct PersistedQueries {
+    cache: Mutex<Lru>,
+    manifest: HashMap<String, String>,
+    /// 为 true 时只执行清单中登记过的查询
+    allow_list_only: bool,
+}
+
+impl PersistedQueries {
+    pub fn new(capacity: usize) -> Self {
+        let manifest = serde_json::from_str(MANIFEST).expect("Invalid persisted-queries.json");
+        Self {
+            cache: Mutex::new(Lru::new(capacity)),
+            manifest,
+            // 开发时需要随时修改查询，只在 release 构建中启用白名单
+            allow_list_only: cfg!(all(
+                feature = "persisted-queries-only",
+                not(debug_assertions)
+            )),
+        }
+    }
+
+    /// 查询文本是否允许执行，供不经过 APQ 的订阅使用
+    pub fn is_allowed(&self, query: &str) -> bool {
+        !self.allow_list_only || self.manifest.contains_key(&sha256(query))
+    }
+
+    pub async fn execute_batch(
+        &self,
+        request: &PersistedBatchRequest,
+        limits: &QueryLimits,
+        schema: &Schema,
+        context: &Context,
+    ) -> GraphQLBatchResponse<CustomScalarValue> {
+        match request {
+            PersistedBatchRequest::Single(request) => {
+                GraphQLBatchResponse::Single(self.execute(request, limits, schema, context).await)
+            }
+            PersistedBatchRequest::Batch(requests) => {
+                let futures = requests
+                    .iter()
+                    .map(|request| self.execute(request, limits, schema, context));
+                GraphQLBatchResponse::Batch(juniper::futures::future::join_all(futures).await)
+            }
+        }
+    }
+
+    pub async fn execute(
+        &self,
+        request: &PersistedRequest,
+        limits: &QueryLimits,
+        schema: &Schema,
+        context: &Context,
+    ) -> GraphQLResponse<CustomScalarValue> {
+        let document = match self.lookup(request) {
+            Ok(Lookup::Cached(document)) => document,
+            Ok(Lookup::Text(hash, query)) => match parse_and_validate(query, schema) {
+                // 解析和校验失败的查询不进入缓存
+                Ok(document) => {
+                    self.cache.lock().unwrap().insert(hash, document.clone());
+                    document
+                }
+                Err(e) => return GraphQLResponse::from_result(Err(e)),
+            },
+            Err(e) => return GraphQLResponse::error(e),
+        };
+        let result = execute_document(
+            &document.get().0,
+            request.operation_name.as_deref(),
+            &request.variables(),
+            limits,
+            schema,
+            context,
+        )
+        .await;
+        GraphQLResponse::from_result(result)
+    }
+
+    /// 在缓存中查找已校验的文档，未命中时确定要解析的查询文本
+    fn lookup(&self, request: &PersistedRequest) -> Result<Lookup, FieldError<CustomScalarValue>> {
+        let persisted = request
+            .extensions
+            .as_ref()
+            .and_then(|it| it.persisted_query.as_ref());
+        if let Some(persisted) = persisted {
+            if persisted.version != 1 {
+                return Err(FieldError::new(
+                    "PersistedQueryNotSupported",
+                    graphql_value!({ "code": "PERSISTED_QUERY_NOT_SUPPORTED" }),
+                ));
+            }
+        }
+        let hash = match (request.query.as_deref(), persisted) {
+            (Some(query), Some(persisted)) => {
+                let hash = sha256(query);
+                if hash != persisted.sha256_hash.to_lowercase() {
+                    return Err(FieldError::new(
+                        "Provided sha256Hash does not match query",
+                        graphql_value!({ "code": "PERSISTED_QUERY_HASH_MISMATCH" }),
+                    ));
+                }
+                hash
+            }
+            (Some(query), None) => sha256(query),
+            (None, Some(persisted)) => persisted.sha256_hash.to_lowercase(),
+            (None, None) => {
+                return Err(FieldError::new(
+                    "Either 'query' or 'extensions.persistedQuery' must be provided",
+                    graphql_value!({ "code": "BAD_REQUEST" }),
+                ));
+            }
+        };
+
+        if self.allow_list_only && !self.manifest.contains_key(&hash) {
+            return Err(FieldError::new(
+                "Query is not registered in the persisted query manifest",
+                graphql_value!({ "code": "PERSISTED_QUERY_NOT_ALLOWED" }),
+            ));
+        }
+        if let Some(document) = self.cache.lock().unwrap().get(&hash) {
+            return Ok(Lookup::Cached(document));
+        }
+        let query = request
+            .query
+            .as_deref()
+            .or_else(|| self.manifest.get(&hash).map(String::as_str));
+        match query {
+            Some(query) => Ok(Lookup::Text(hash, Arc::from(query))),
+            // 按 APQ 协议，客户端收到该错误后会附带完整的查询文本重试
+            None => Err(FieldError::new(
+                "PersistedQueryNotFound",
+                graphql_value!({ "code": "PERSISTED_QUERY_NOT_FOUND" }),
+            )),
+        }
+    }
+}
+
+enum Lookup {
+    Cached(ValidatedDocument),
+    /// 未命中缓存，`(hash, 查询文本)`
+    Text(String, Arc<str>),
+}
+
+type ExecutionOutput = (
+    Value<CustomScalarValue>,
+    Vec<ExecutionError<CustomScalarValue>>,
+);
+
+fn parse_and_validate(query: Arc<str>, schema: &Schema) -> Result<ValidatedDocument, GraphQLError> {
+    let document: Yoke<ParsedDocument<'static>, Arc<str>> =
+        Yoke::try_attach_to_cart(query, |query: &str| {
+            parse_document_source(query, &schema.schema).map(ParsedDocument)
+        })
+        .map_err(GraphQLError::ParseError)?;
+    let definitions = &document.get().0;
+    let mut ctx = ValidatorContext::new(&schema.schema, definitions);
+    visit_all_rules(&mut ctx, definitions);
+    let errors = ctx.into_errors();
+    if !errors.is_empty() {
+        return Err(GraphQLError::ValidationError(errors));
+    }
+    Ok(Arc::new(document))
+}
+
+/// 与 `juniper::execute` 相同，但执行的是已校验的文档，并在执行前检查 `limits`
+async fn execute_document(
+    document: &Document<'_, CustomScalarValue>,
+    operation_name: Option<&str>,
+    variables: &Variables<CustomScalarValue>,
+    limits: &QueryLimits,
+    schema: &Schema,
+    context: &Context,
+) -> Result<ExecutionOutput, GraphQLError> {
+    let operation = get_operation(document, operation_name)?;
+    let errors = validate_input_values(variables, operation, &schema.schema);
+    if !errors.is_empty() {
+        return Err(GraphQLError::ValidationError(errors));
+    }
+    if let Err(e) = limits.check(document, &operation.item, variables) {
+        return Ok((Value::null(), vec![ExecutionError::at_origin(e)]));
+    }
+    execute_validated_query_async(document, operation, schema, variables, context).await
+}
+
+fn sha256(query: &str) -> String {
+    Sha256::digest(query.as_bytes())
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+/// 容量很小的 LRU，命中时把条目移到队尾，超出容量时淘汰队首
+struct Lru {
+    capacity: usize,
+    entries: HashMap<String, ValidatedDocument>,
+    order: VecDeque<String>,
+}
+
+impl Lru {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: HashMap::with_capacity(capacity),
+            order: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    fn get(&mut self, key: &str) -> Option<ValidatedDocument> {
+        let value = self.entries.get(key)?.clone();
+        self.touch(key);
+        Some(value)
+    }
+
+    fn insert(&mut self, key: String, value: ValidatedDocument) {
+        if self.entries.insert(key.clone(), value).is_some() {
+            self.touch(&key);
+            return;
+        }
+        self.order.push_back(key);
+        while self.order.len() > self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.entries.remove(&oldest);
+            }
+        }
+    }
+
+    fn touch(&mut self, key: &str) {
+        if let Some(index) = self.order.iter().position(|it| it == key) {
+            if let Some(key) = self.order.remove(index) {
+                self.order.push_back(key);
+            }
+        }
+    }
+}
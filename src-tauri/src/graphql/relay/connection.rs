@@ -1,10 +1,10 @@
-use super::{ConnectionEdge, ConnectionNode, Cursor};
+use super::{ConnectionEdge, ConnectionNode, EdgeData, EncodedCursor};
 use juniper::{
     macros::reflect::{BaseSubTypes, BaseType, Type, Types, WrappedType, WrappedValue},
     marker::IsOutputType,
-    meta::MetaType,
-    Arguments, Context, ExecutionResult, Executor, GraphQLObject, GraphQLType, GraphQLValue,
-    GraphQLValueAsync, Registry, ScalarValue,
+    meta::{Field, MetaType},
+    Arguments, Context, ExecutionResult, Executor, FieldError, GraphQLObject, GraphQLType,
+    GraphQLValue, GraphQLValueAsync, Registry, ScalarValue,
 };
 
 #[derive(Debug, Clone, Default, GraphQLObject)]
@@ -14,26 +14,111 @@ pub struct PageInfo {
     /// 是否存在下一页（当使用 first/after 时可用）
     pub(super) has_next_page: bool,
     /// 当前页第一条记录的游标
-    pub(super) start_cursor: Option<Cursor>,
+    pub(super) start_cursor: Option<EncodedCursor>,
     /// 当前页最后一条记录的游标
-    pub(super) end_cursor: Option<Cursor>,
+    pub(super) end_cursor: Option<EncodedCursor>,
 }
 
-#[derive(Debug, Default)]
-pub struct Connection<N> {
-    pub(super) edges: Vec<ConnectionEdge<N>>,
+#[derive(Debug)]
+pub struct Connection<N, E = ()>
+where
+    N: ConnectionNode,
+    E: EdgeData<N>,
+{
+    pub(super) edges: Vec<ConnectionEdge<N, E>>,
     pub(super) page_info: PageInfo,
     pub(super) total_count: i32,
+    /// 只有查询中选择了附加字段时才会加载，见 `Connection::load_fields`
+    pub(super) fields: Option<E::ConnectionFields>,
+}
+
+/// 节点在连接上声明的附加字段（例如聚合统计），通常通过 `#[derive(ConnectionFields)]` 实现
+pub trait ConnectionFields: Send + Sync {
+    /// 附加字段的 GraphQL 名称，用于判断查询中是否选择了它们
+    const FIELD_NAMES: &'static [&'static str];
+
+    fn fields<'r, S>(registry: &mut Registry<'r, S>) -> Vec<Field<'r, S>>
+    where
+        S: ScalarValue + 'r;
+
+    /// 解析附加字段，不属于附加字段的返回 `None`
+    fn resolve_field<C, S>(
+        &self,
+        field_name: &str,
+        executor: &Executor<C, S>,
+    ) -> Option<ExecutionResult<S>>
+    where
+        S: ScalarValue;
+}
+
+impl ConnectionFields for () {
+    const FIELD_NAMES: &'static [&'static str] = &[];
+
+    fn fields<'r, S>(_registry: &mut Registry<'r, S>) -> Vec<Field<'r, S>>
+    where
+        S: ScalarValue + 'r,
+    {
+        Vec::new()
+    }
+
+    fn resolve_field<C, S>(
+        &self,
+        _field_name: &str,
+        _executor: &Executor<C, S>,
+    ) -> Option<ExecutionResult<S>>
+    where
+        S: ScalarValue,
+    {
+        None
+    }
+}
+
+/// 查询中是否选择了 `F` 声明的附加字段
+pub(super) fn fields_selected<F, C, S>(executor: &Executor<'_, '_, C, S>) -> bool
+where
+    F: ConnectionFields,
+    S: ScalarValue,
+{
+    let children: juniper::LookAheadChildren<'_, S> = executor.look_ahead().children();
+    let selected = children
+        .iter()
+        .any(|sel| F::FIELD_NAMES.contains(&sel.field_original_name()));
+    selected
+}
+
+/// 解析连接上内置字段以外的字段，`type_name` 用于错误信息
+pub(super) fn resolve_extra_field<F, C, S>(
+    fields: Option<&F>,
+    type_name: &str,
+    field_name: &str,
+    executor: &Executor<C, S>,
+) -> ExecutionResult<S>
+where
+    F: ConnectionFields,
+    S: ScalarValue,
+{
+    if !F::FIELD_NAMES.contains(&field_name) {
+        panic!("Field {} not found on type {}", field_name, type_name);
+    }
+    match fields {
+        Some(fields) => fields
+            .resolve_field(field_name, executor)
+            .unwrap_or_else(|| panic!("Field {} not found on type {}", field_name, type_name)),
+        None => Err(FieldError::from(format!(
+            "Field '{field_name}' was selected but not loaded"
+        ))),
+    }
 }
 
-impl<N, S> GraphQLType<S> for Connection<N>
+impl<N, E, S> GraphQLType<S> for Connection<N, E>
 where
     N: GraphQLType<S> + ConnectionNode,
+    E: EdgeData<N>,
     N::Context: Context,
     S: ScalarValue,
 {
     fn name(_info: &<N as GraphQLValue<S>>::TypeInfo) -> Option<&str> {
-        Some(N::CONNECTION_TYPE_NAME)
+        Some(E::CONNECTION_TYPE_NAME)
     }
     fn meta<'r>(
         info: &<N as GraphQLValue<S>>::TypeInfo,
@@ -42,9 +127,9 @@ where
     where
         S: 'r,
     {
-        let fields = &[
+        let mut fields = vec![
             registry
-                .field::<&Vec<ConnectionEdge<N>>>("edges", info)
+                .field::<&Vec<ConnectionEdge<N, E>>>("edges", info)
                 .description("分页连接的核心数据载体，包含节点及其关联的元数据（如游标）"),
             registry
                 .field::<&Vec<N>>("nodes", info)
@@ -56,13 +141,17 @@ where
                 .field::<&PageInfo>("pageInfo", &())
                 .description("分页控制元数据，用于确定是否可翻页及边界游标"),
         ];
-        registry.build_object_type::<Self>(info, fields).into_meta()
+        fields.extend(E::ConnectionFields::fields(registry));
+        registry
+            .build_object_type::<Self>(info, &fields)
+            .into_meta()
     }
 }
 
-impl<N, S> GraphQLValue<S> for Connection<N>
+impl<N, E, S> GraphQLValue<S> for Connection<N, E>
 where
     N: GraphQLType<S> + ConnectionNode,
+    E: EdgeData<N>,
     N::Context: Context,
     S: ScalarValue,
 {
@@ -87,7 +176,7 @@ where
             }
             "pageInfo" => executor.resolve_with_ctx(&(), &self.page_info),
             "totalCount" => executor.resolve_with_ctx(&(), &self.total_count),
-            _ => panic!("Field {} not found on type Connection", field_name),
+            _ => resolve_extra_field(self.fields.as_ref(), "Connection", field_name, executor),
         }
     }
     fn concrete_type_name(&self, _context: &Self::Context, info: &Self::TypeInfo) -> String {
@@ -95,9 +184,10 @@ where
     }
 }
 
-impl<N, S> GraphQLValueAsync<S> for Connection<N>
+impl<N, E, S> GraphQLValueAsync<S> for Connection<N, E>
 where
     N: GraphQLType<S> + GraphQLValueAsync<S> + ConnectionNode + Sync + Send,
+    E: EdgeData<N> + Sync + Send,
     N::TypeInfo: Sync,
     N::Context: Context + Sync,
     S: ScalarValue + Send + Sync,
@@ -118,7 +208,7 @@ where
                 }
                 "pageInfo" => executor.resolve_with_ctx(&(), &self.page_info),
                 "totalCount" => executor.resolve_with_ctx(&(), &self.total_count),
-                _ => panic!("Field {} not found on type Connection", field_name),
+                _ => resolve_extra_field(self.fields.as_ref(), "Connection", field_name, executor),
             }
         };
         use juniper::futures::future;
@@ -126,37 +216,41 @@ where
     }
 }
 
-impl<N, S> IsOutputType<S> for Connection<N>
+impl<N, E, S> IsOutputType<S> for Connection<N, E>
 where
     N: GraphQLType<S> + ConnectionNode,
+    E: EdgeData<N>,
     S: ScalarValue,
     <N as GraphQLValue<S>>::Context: Context,
 {
 }
 
-impl<N, S> BaseType<S> for Connection<N>
+impl<N, E, S> BaseType<S> for Connection<N, E>
 where
     N: GraphQLType<S> + ConnectionNode,
+    E: EdgeData<N>,
     S: ScalarValue,
 {
-    const NAME: Type = N::CONNECTION_TYPE_NAME;
+    const NAME: Type = E::CONNECTION_TYPE_NAME;
 }
 
-impl<N, S> BaseSubTypes<S> for Connection<N>
+impl<N, E, S> BaseSubTypes<S> for Connection<N, E>
 where
     N: GraphQLType<S> + ConnectionNode + BaseType<S>,
+    E: EdgeData<N>,
     N::Context: Context,
     S: ScalarValue,
 {
     const NAMES: Types = &[
-        <ConnectionEdge<N> as BaseType<S>>::NAME,
+        <ConnectionEdge<N, E> as BaseType<S>>::NAME,
         <PageInfo as BaseType<S>>::NAME,
     ];
 }
 
-impl<N, S> WrappedType<S> for Connection<N>
+impl<N, E, S> WrappedType<S> for Connection<N, E>
 where
     N: GraphQLType<S> + ConnectionNode,
+    E: EdgeData<N>,
     S: ScalarValue,
 {
     const VALUE: WrappedValue = 1;
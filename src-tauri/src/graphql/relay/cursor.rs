@@ -1,52 +1,439 @@
+use std::fmt;
+use std::io::Write;
+use std::path::Path;
+use std::sync::OnceLock;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::{anyhow, Context};
+use hmac::{Hmac, Mac};
+use juniper::{graphql_value, FieldError, GraphQLScalar, IntoFieldError, ScalarValue};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
 use crate::{graphql::scalar, utils::base64_url};
-use juniper::GraphQLScalar;
 
-#[derive(Debug, Clone, GraphQLScalar)]
-#[graphql(with = cursor_scalar, parse_token(String))]
+/// 当前的编码版本，写在游标的第一个字节；格式变化时递增，旧游标会被明确拒绝
+const CURSOR_VERSION: u8 = 2;
+/// 附在游标末尾的 HMAC-SHA256 截断长度
+const MAC_LEN: usize = 16;
+const KEY_LEN: usize = 32;
+/// 筛选条件摘要的长度，只用于区分同一连接类型下的不同结果集
+const SCOPE_LEN: usize = 12;
+
+/// 游标记录了生成它的连接、筛选条件、排序方式、各排序键的值以及作为决胜键的 id
+#[derive(Debug, Clone)]
 pub struct Cursor {
+    pub(crate) connection: String,
+    /// 生成游标时连接的筛选条件摘要，见 `Cursor::scope_of`
+    pub(crate) scope: String,
+    pub(crate) order: String,
+    pub(crate) values: Vec<CursorValue>,
     pub(crate) id: scalar::ID,
-    pub(crate) created_at: scalar::Timestamp,
 }
 
-mod cursor_scalar {
+/// 排序键在游标中的取值
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum CursorValue {
+    Boolean(bool),
+    Integer(i64),
+    Float(f64),
+    Text(String),
+}
+
+impl From<&bool> for CursorValue {
+    fn from(value: &bool) -> Self {
+        Self::Boolean(*value)
+    }
+}
+
+impl From<&i64> for CursorValue {
+    fn from(value: &i64) -> Self {
+        Self::Integer(*value)
+    }
+}
+
+impl From<&f64> for CursorValue {
+    fn from(value: &f64) -> Self {
+        Self::Float(*value)
+    }
+}
+
+impl From<&String> for CursorValue {
+    fn from(value: &String) -> Self {
+        Self::Text(value.clone())
+    }
+}
+
+impl From<&scalar::ID> for CursorValue {
+    fn from(value: &scalar::ID) -> Self {
+        Self::Integer((*value).into())
+    }
+}
+
+impl From<&scalar::Timestamp> for CursorValue {
+    fn from(value: &scalar::Timestamp) -> Self {
+        Self::Integer((*value).into())
+    }
+}
+
+/// 不透明的分页游标，带有版本号和签名，只能原样传回生成它的连接
+// 解析参数时只要求是字符串，解码和校验推迟到 `Pagination::new`，以便按失败原因返回不同的错误码
+#[derive(Debug, Clone, PartialEq, GraphQLScalar)]
+#[graphql(name = "Cursor", with = encoded_cursor_scalar, parse_token(String))]
+pub struct EncodedCursor(String);
+
+mod encoded_cursor_scalar {
     use super::*;
-    use juniper::{InputValue, ScalarValue, Value};
+    use juniper::{InputValue, Value};
 
-    pub(super) fn to_output<S: ScalarValue>(v: &Cursor) -> Value<S> {
-        Value::Scalar(String::from(v).into())
+    pub(super) fn to_output<S: ScalarValue>(v: &EncodedCursor) -> Value<S> {
+        Value::Scalar(v.0.clone().into())
     }
-    pub(super) fn from_input<S: ScalarValue>(v: &InputValue<S>) -> Result<Cursor, String> {
+    pub(super) fn from_input<S: ScalarValue>(v: &InputValue<S>) -> Result<EncodedCursor, String> {
         v.as_string_value()
+            .map(|v| EncodedCursor(v.to_string()))
             .ok_or_else(|| format!("Expected `String`, found: {v}"))
-            .and_then(|v| match Cursor::try_from(v) {
-                Ok(cursor) => Ok(cursor),
-                Err(e) => Err(e.to_string()),
-            })
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CursorError {
+    /// 不是本应用生成的游标，或编码版本已不再支持
+    Malformed,
+    /// 签名校验失败，游标被修改过或来自其他安装
+    Tampered,
+    Expired,
+    /// 游标来自另一个连接，或同一连接类型下的另一组筛选条件
+    WrongConnection,
+}
+
+impl CursorError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::Malformed => "CURSOR_MALFORMED",
+            Self::Tampered => "CURSOR_TAMPERED",
+            Self::Expired => "CURSOR_EXPIRED",
+            Self::WrongConnection => "CURSOR_CONNECTION_MISMATCH",
+        }
+    }
+}
+
+impl fmt::Display for CursorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Malformed => "Invalid cursor format",
+            Self::Tampered => "Cursor signature does not match",
+            Self::Expired => "Cursor has expired",
+            Self::WrongConnection => "Cursor was created for a different connection or filter",
+        })
+    }
+}
+
+impl std::error::Error for CursorError {}
+
+impl<S: ScalarValue> IntoFieldError<S> for CursorError {
+    fn into_field_error(self) -> FieldError<S> {
+        FieldError::new(self, graphql_value!({ "code": (self.code()) }))
+    }
+}
+
+/// 签名游标使用的密钥和有效期
+///
+/// 密钥在首次启动时随机生成并保存在应用数据目录中，重装或清空数据后旧游标会被视为篡改
+pub struct CursorKey {
+    secret: [u8; KEY_LEN],
+    ttl: Option<Duration>,
+}
+
+static CURSOR_KEY: OnceLock<CursorKey> = OnceLock::new();
+
+impl CursorKey {
+    /// 读取 `path` 中保存的密钥，文件不存在时生成一个新密钥并写入
+    pub fn load_or_create(path: &Path) -> anyhow::Result<Self> {
+        let secret = match std::fs::read(path) {
+            Ok(bytes) => <[u8; KEY_LEN]>::try_from(bytes.as_slice())
+                .map_err(|_| anyhow!("Cursor key {} has an invalid length", path.display()))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                let secret = random_secret()?;
+                let mut options = std::fs::OpenOptions::new();
+                options.write(true).create_new(true);
+                #[cfg(unix)]
+                std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+                options
+                    .open(path)
+                    .and_then(|mut file| file.write_all(&secret))
+                    .with_context(|| {
+                        format!("Failed to write the cursor key {}", path.display())
+                    })?;
+                secret
+            }
+            Err(e) => {
+                return Err(e)
+                    .with_context(|| format!("Failed to read the cursor key {}", path.display()))
+            }
+        };
+        Ok(Self { secret, ttl: None })
+    }
+
+    /// 新生成的游标在 `ttl` 之后过期，默认不过期
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    /// 设为全局密钥，需要在生成第一个游标之前调用
+    pub fn install(self) -> anyhow::Result<()> {
+        CURSOR_KEY
+            .set(self)
+            .map_err(|_| anyhow!("Cursor key is already installed"))
+    }
+
+    /// 未安装密钥时（例如独立运行 schema）使用进程内的临时密钥，游标只在本次运行中有效
+    fn current() -> &'static Self {
+        CURSOR_KEY.get_or_init(|| Self {
+            secret: random_secret().expect("Failed to generate a cursor key"),
+            ttl: None,
+        })
+    }
+
+    fn mac(&self, data: &[u8]) -> Hmac<Sha256> {
+        let mut mac = Hmac::<Sha256>::new_from_slice(&self.secret).expect("HMAC accepts any key");
+        mac.update(data);
+        mac
+    }
+
+    /// 编码为 `base64url(版本 || JSON 负载 || HMAC)`，负载中包含可选的过期时间（Unix 秒）
+    fn encode(&self, cursor: &Cursor) -> EncodedCursor {
+        let expires_at = self.ttl.map(|ttl| unix_now() + ttl.as_secs() as i64);
+        let payload = (
+            &cursor.connection,
+            &cursor.scope,
+            &cursor.order,
+            &cursor.values,
+            i64::from(cursor.id),
+            expires_at,
+        );
+        let mut bytes = vec![CURSOR_VERSION];
+        // 序列化由字符串、基础类型组成的元组不会失败
+        serde_json::to_writer(&mut bytes, &payload).unwrap();
+        let tag = self.mac(&bytes).finalize().into_bytes();
+        bytes.extend_from_slice(&tag[..MAC_LEN]);
+        EncodedCursor(base64_url::encode(&bytes))
+    }
+
+    fn decode(&self, value: &str) -> Result<Cursor, CursorError> {
+        let bytes = base64_url::decode(value).map_err(|_| CursorError::Malformed)?;
+        if bytes.len() <= 1 + MAC_LEN || bytes[0] != CURSOR_VERSION {
+            return Err(CursorError::Malformed);
+        }
+        let (data, tag) = bytes.split_at(bytes.len() - MAC_LEN);
+        self.mac(data)
+            .verify_truncated_left(tag)
+            .map_err(|_| CursorError::Tampered)?;
+        let (connection, scope, order, values, id, expires_at) =
+            serde_json::from_slice::<(String, String, String, Vec<CursorValue>, i64, Option<i64>)>(
+                &data[1..],
+            )
+            .map_err(|_| CursorError::Malformed)?;
+        // 过期时间在签名范围内，无法被单独修改
+        if expires_at.is_some_and(|it| it <= unix_now()) {
+            return Err(CursorError::Expired);
+        }
+        Ok(Cursor {
+            connection,
+            scope,
+            order,
+            values,
+            id: scalar::ID::from(id),
+        })
+    }
+}
+
+fn random_secret() -> anyhow::Result<[u8; KEY_LEN]> {
+    let mut secret = [0u8; KEY_LEN];
+    getrandom::getrandom(&mut secret)
+        .map_err(|e| anyhow!("Failed to generate a cursor key: {e}"))?;
+    Ok(secret)
+}
+
+fn unix_now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |it| it.as_secs() as i64)
+}
+
 impl Cursor {
-    pub fn new(id: scalar::ID, created_at: scalar::Timestamp) -> Self {
-        Self { created_at, id }
+    pub fn new(
+        connection: impl Into<String>,
+        order: String,
+        values: Vec<CursorValue>,
+        id: scalar::ID,
+    ) -> Self {
+        Self {
+            connection: connection.into(),
+            scope: String::new(),
+            order,
+            values,
+            id,
+        }
+    }
+
+    /// 筛选条件的摘要
+    ///
+    /// 同一种连接类型会出现在多个字段上（例如 `listTodos`、`trash` 和 `Todo.children`），
+    /// 各字段传给 loader 的筛选条件不同，摘要用来保证游标只在生成它的结果集中使用
+    pub fn scope_of(filter: &impl fmt::Debug) -> String {
+        let digest = Sha256::digest(format!("{filter:?}").as_bytes());
+        base64_url::encode(&digest[..SCOPE_LEN])
+    }
+
+    pub fn with_scope(mut self, scope: &str) -> Self {
+        self.scope = scope.to_string();
+        self
+    }
+
+    /// 游标只能用于生成它的连接和筛选条件
+    pub fn check_connection(&self, connection: &str, scope: &str) -> Result<(), CursorError> {
+        if self.connection == connection && self.scope == scope {
+            Ok(())
+        } else {
+            Err(CursorError::WrongConnection)
+        }
     }
 }
 
-impl From<&Cursor> for String {
+/// 使用全局密钥签名，见 `CursorKey::encode`
+impl From<&Cursor> for EncodedCursor {
     fn from(cursor: &Cursor) -> Self {
-        base64_url::encode(format!("{}:{}", cursor.created_at, cursor.id).as_bytes())
+        CursorKey::current().encode(cursor)
     }
 }
 
 impl<'a> TryFrom<&'a str> for Cursor {
-    type Error = &'static str;
+    type Error = CursorError;
     fn try_from(value: &'a str) -> Result<Self, Self::Error> {
-        static ERR_MSG: &str = "Invalid cursor format";
-        let bytes = base64_url::decode(value).map_err(|_| ERR_MSG)?;
-        let str = std::str::from_utf8(&bytes).map_err(|_| ERR_MSG)?;
-        let (created_at, id) = str.split_once(":").ok_or(ERR_MSG)?;
-        Ok(Self {
-            id: scalar::ID::from(id.parse::<i64>().map_err(|_| ERR_MSG)?),
-            created_at: scalar::Timestamp::from(created_at.parse::<i64>().map_err(|_| ERR_MSG)?),
-        })
+        CursorKey::current().decode(value)
+    }
+}
+
+impl TryFrom<&EncodedCursor> for Cursor {
+    type Error = CursorError;
+    fn try_from(value: &EncodedCursor) -> Result<Self, Self::Error> {
+        Self::try_from(value.0.as_str())
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(ttl: Option<Duration>) -> CursorKey {
+        CursorKey {
+            secret: [7; KEY_LEN],
+            ttl,
+        }
+    }
+
+    fn cursor() -> Cursor {
+        Cursor::new(
+            "TodoConnection",
+            String::from("description:ASC"),
+            vec![
+                CursorValue::Text(String::from("milk")),
+                CursorValue::Integer(3),
+            ],
+            scalar::ID::from(42),
+        )
+        .with_scope(&Cursor::scope_of(&Some(1)))
+    }
+
+    #[test]
+    fn round_trip() {
+        let key = key(Some(Duration::from_secs(60)));
+        let decoded = key.decode(&key.encode(&cursor()).0).unwrap();
+        assert_eq!(decoded.connection, "TodoConnection");
+        assert_eq!(decoded.scope, Cursor::scope_of(&Some(1)));
+        assert_eq!(decoded.order, "description:ASC");
+        assert_eq!(decoded.values, cursor().values);
+        assert_eq!(i64::from(decoded.id), 42);
+        let scope = Cursor::scope_of(&Some(1));
+        assert!(decoded.check_connection("TodoConnection", &scope).is_ok());
+        assert_eq!(
+            decoded
+                .check_connection("TagConnection", &scope)
+                .unwrap_err(),
+            CursorError::WrongConnection
+        );
+        assert_eq!(
+            decoded
+                .check_connection("TodoConnection", &Cursor::scope_of(&Some(2)))
+                .unwrap_err(),
+            CursorError::WrongConnection
+        );
+    }
+
+    #[test]
+    fn rejects_tampered_cursors() {
+        let key = key(None);
+        let bytes = base64_url::decode(&key.encode(&cursor()).0).unwrap();
+        let (data, tag) = bytes.split_at(bytes.len() - MAC_LEN);
+        // 解出负载后逐个修改字段，沿用原来的签名重新编码
+        let payload: serde_json::Value = serde_json::from_slice(&data[1..]).unwrap();
+        let edits: [(usize, serde_json::Value); 4] = [
+            (0, "TagConnection".into()),
+            (1, Cursor::scope_of(&Some(2)).into()),
+            (3, serde_json::json!(["milk", 4])),
+            (4, 43.into()),
+        ];
+        for (index, value) in edits {
+            let mut payload = payload.clone();
+            assert_ne!(payload[index], value);
+            payload[index] = value;
+            let mut forged = vec![CURSOR_VERSION];
+            serde_json::to_writer(&mut forged, &payload).unwrap();
+            forged.extend_from_slice(tag);
+            assert_eq!(
+                key.decode(&base64_url::encode(&forged)).unwrap_err(),
+                CursorError::Tampered
+            );
+        }
+        // 其他安装的密钥签名的游标
+        let other = CursorKey {
+            secret: [8; KEY_LEN],
+            ttl: None,
+        };
+        assert_eq!(
+            key.decode(&other.encode(&cursor()).0).unwrap_err(),
+            CursorError::Tampered
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_cursors() {
+        let key = key(None);
+        assert_eq!(
+            key.decode("not a cursor!").unwrap_err(),
+            CursorError::Malformed
+        );
+        assert_eq!(
+            key.decode(&base64_url::encode(b"short")).unwrap_err(),
+            CursorError::Malformed
+        );
+        let mut bytes = base64_url::decode(&key.encode(&cursor()).0).unwrap();
+        bytes[0] = CURSOR_VERSION + 1;
+        assert_eq!(
+            key.decode(&base64_url::encode(&bytes)).unwrap_err(),
+            CursorError::Malformed
+        );
+    }
+
+    #[test]
+    fn rejects_expired_cursors() {
+        let key = key(Some(Duration::ZERO));
+        assert_eq!(
+            key.decode(&key.encode(&cursor()).0).unwrap_err(),
+            CursorError::Expired
+        );
+    }
+}
@@ -1,34 +1,91 @@
 use juniper::{
     macros::reflect::{BaseSubTypes, BaseType, Type, Types, WrappedType, WrappedValue},
     marker::IsOutputType,
-    meta::MetaType,
+    meta::{Field, MetaType},
     Arguments, Context, ExecutionResult, Executor, GraphQLType, GraphQLValue, GraphQLValueAsync,
     Registry, ScalarValue,
 };
 
-use super::ConnectionNode;
-use super::Cursor;
+use super::{ConnectionFields, ConnectionNode, ConnectionOrder};
+use super::{Cursor, EncodedCursor};
 
 #[derive(Debug)]
-pub struct ConnectionEdge<N> {
+pub struct ConnectionEdge<N, E = ()> {
     pub(super) node: N,
     pub(super) cursor: Cursor,
+    pub(super) data: E,
 }
 
-impl<N, S> GraphQLType<S> for ConnectionEdge<N>
+impl<N: ConnectionNode> ConnectionEdge<N> {
+    /// 在连接之外单独构造一条边，例如变更结果中返回新增的节点
+    ///
+    /// `filter` 需与要插入的连接传给 loader 的筛选条件相同，游标才能在该连接上继续分页
+    pub fn new(node: N, filter: &impl std::fmt::Debug, order: &N::Order) -> Self {
+        Self {
+            cursor: node.cursor(order).with_scope(&Cursor::scope_of(filter)),
+            node,
+            data: (),
+        }
+    }
+}
+
+/// 附加在边上的数据（例如搜索结果的相关度），其字段会与 `node`、`cursor` 一起暴露在边类型上
+///
+/// 同一种节点搭配不同的附加数据会产生不同的连接类型，因此类型名由附加数据决定
+pub trait EdgeData<N: ConnectionNode> {
+    type Order: ConnectionOrder;
+    type ConnectionFields: ConnectionFields;
+    const CONNECTION_TYPE_NAME: &'static str;
+    const EDGE_TYPE_NAME: &'static str;
+
+    fn cursor(&self, node: &N, order: &Self::Order) -> Cursor;
+
+    fn fields<'r, S>(_registry: &mut Registry<'r, S>) -> Vec<Field<'r, S>>
+    where
+        S: ScalarValue + 'r,
+    {
+        Vec::new()
+    }
+
+    /// 解析附加字段，不属于附加数据的字段返回 `None`
+    fn resolve_field<C, S>(
+        &self,
+        _field_name: &str,
+        _executor: &Executor<C, S>,
+    ) -> Option<ExecutionResult<S>>
+    where
+        S: ScalarValue,
+    {
+        None
+    }
+}
+
+impl<N: ConnectionNode> EdgeData<N> for () {
+    type Order = N::Order;
+    type ConnectionFields = N::ConnectionFields;
+    const CONNECTION_TYPE_NAME: &'static str = N::CONNECTION_TYPE_NAME;
+    const EDGE_TYPE_NAME: &'static str = N::EDGE_TYPE_NAME;
+
+    fn cursor(&self, node: &N, order: &Self::Order) -> Cursor {
+        node.cursor(order)
+    }
+}
+
+impl<N, E, S> GraphQLType<S> for ConnectionEdge<N, E>
 where
     N: GraphQLType<S> + ConnectionNode,
     N::Context: Context,
+    E: EdgeData<N>,
     S: ScalarValue,
 {
     fn name(_info: &Self::TypeInfo) -> Option<&str> {
-        Some(N::EDGE_TYPE_NAME)
+        Some(E::EDGE_TYPE_NAME)
     }
     fn meta<'r>(info: &Self::TypeInfo, registry: &mut Registry<'r, S>) -> MetaType<'r, S>
     where
         S: 'r,
     {
-        let fields = &[
+        let mut fields = vec![
             registry
                 .field::<&N>("node", info)
                 .description("表示分页结果中的单个数据节点，包含实际业务数据"),
@@ -36,14 +93,18 @@ where
                 .field::<&String>("cursor", &())
                 .description("唯一标识分页位置的游标"),
         ];
-        registry.build_object_type::<Self>(info, fields).into_meta()
+        fields.extend(E::fields(registry));
+        registry
+            .build_object_type::<Self>(info, &fields)
+            .into_meta()
     }
 }
 
-impl<N, S> GraphQLValue<S> for ConnectionEdge<N>
+impl<N, E, S> GraphQLValue<S> for ConnectionEdge<N, E>
 where
     N: GraphQLType<S> + ConnectionNode,
     N::Context: Context,
+    E: EdgeData<N>,
     S: ScalarValue,
 {
     type Context = N::Context;
@@ -60,8 +121,11 @@ where
     ) -> ExecutionResult<S> {
         match field_name {
             "node" => executor.resolve_with_ctx(info, &self.node),
-            "cursor" => executor.resolve_with_ctx(&(), &self.cursor),
-            _ => panic!("Field {} not found on type ConnectionEdge", field_name),
+            "cursor" => executor.resolve_with_ctx(&(), &EncodedCursor::from(&self.cursor)),
+            _ => self
+                .data
+                .resolve_field(field_name, executor)
+                .unwrap_or_else(|| panic!("Field {} not found on type ConnectionEdge", field_name)),
         }
     }
     fn concrete_type_name(&self, _context: &Self::Context, info: &Self::TypeInfo) -> String {
@@ -69,11 +133,12 @@ where
     }
 }
 
-impl<N, S> GraphQLValueAsync<S> for ConnectionEdge<N>
+impl<N, E, S> GraphQLValueAsync<S> for ConnectionEdge<N, E>
 where
     N: GraphQLType<S> + GraphQLValueAsync<S> + ConnectionNode + Sync + Send,
     N::TypeInfo: Sync,
     N::Context: Context + Sync,
+    E: EdgeData<N> + Sync + Send,
     S: ScalarValue + Send + Sync,
 {
     fn resolve_field_async<'a>(
@@ -86,8 +151,13 @@ where
         let f = async move {
             match field_name {
                 "node" => executor.resolve_with_ctx_async(info, &self.node).await,
-                "cursor" => executor.resolve_with_ctx(&(), &self.cursor),
-                _ => panic!("Field {} not found on type ConnectionEdge", field_name),
+                "cursor" => executor.resolve_with_ctx(&(), &EncodedCursor::from(&self.cursor)),
+                _ => self
+                    .data
+                    .resolve_field(field_name, executor)
+                    .unwrap_or_else(|| {
+                        panic!("Field {} not found on type ConnectionEdge", field_name)
+                    }),
             }
         };
         use juniper::futures::future;
@@ -95,36 +165,43 @@ where
     }
 }
 
-impl<N, S> IsOutputType<S> for ConnectionEdge<N>
+impl<N, E, S> IsOutputType<S> for ConnectionEdge<N, E>
 where
     N: GraphQLType<S> + ConnectionNode,
+    E: EdgeData<N>,
     S: ScalarValue,
     <N as GraphQLValue<S>>::Context: Context,
 {
 }
 
-impl<N, S> BaseType<S> for ConnectionEdge<N>
+impl<N, E, S> BaseType<S> for ConnectionEdge<N, E>
 where
     N: GraphQLType<S> + ConnectionNode,
     N::Context: Context,
+    E: EdgeData<N>,
     S: ScalarValue,
 {
-    const NAME: Type = N::EDGE_TYPE_NAME;
+    const NAME: Type = E::EDGE_TYPE_NAME;
 }
 
-impl<N, S> BaseSubTypes<S> for ConnectionEdge<N>
+impl<N, E, S> BaseSubTypes<S> for ConnectionEdge<N, E>
 where
     N: GraphQLType<S> + ConnectionNode + BaseType<S>,
     N::Context: Context,
+    E: EdgeData<N>,
     S: ScalarValue,
 {
-    const NAMES: Types = &[<N as BaseType<S>>::NAME, <Cursor as BaseType<S>>::NAME];
+    const NAMES: Types = &[
+        <N as BaseType<S>>::NAME,
+        <EncodedCursor as BaseType<S>>::NAME,
+    ];
 }
 
-impl<N, S> WrappedType<S> for ConnectionEdge<N>
+impl<N, E, S> WrappedType<S> for ConnectionEdge<N, E>
 where
     N: GraphQLType<S> + ConnectionNode,
     N::Context: Context,
+    E: EdgeData<N>,
     S: ScalarValue,
 {
     const VALUE: WrappedValue = 1;
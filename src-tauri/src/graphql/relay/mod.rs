@@ -2,75 +2,195 @@ mod connection;
 mod cursor;
 mod edge;
 mod node;
+mod offset;
+mod order;
 mod pagination;
 
 pub use connection::*;
 pub use cursor::*;
 pub use edge::*;
 pub use node::*;
+pub use offset::*;
+pub use order::*;
 pub use pagination::*;
 
-impl<N> Connection<N>
+use juniper::IntoFieldError;
+
+impl<N, E> Connection<N, E>
 where
     N: ConnectionNode,
+    E: EdgeData<N>,
 {
+    /// `rows` 为按查询方向排列的结果（`last` 时为倒序），最多比 `limit` 多一条，这里统一转换为正序
+    ///
+    /// 查询方向上是否还有数据由多取的那一条决定；另一侧需要额外探测，返回值中的游标即为探测的起点
     fn build_connection(
-        pagination: Pagination,
+        pagination: &Pagination,
+        scope: &str,
+        order: &E::Order,
         total_count: i32,
-        edges: Vec<N>,
-    ) -> juniper::FieldResult<Connection<N>> {
-        let edges_len = edges.len() as i32;
-        // 前面有 validate 函数约束了 first 和 last 不能同时存在，故此不做额外的判断
-        let has_next_page = pagination.first.map(|it| edges_len > it).unwrap_or(false);
-        let has_previous_page = pagination.last.map(|it| edges_len > it).unwrap_or(false);
-        // 如果 first，last 都没有传，默认取 10 条，但 has_next_page 和 has_previous_page 永远为 false
-        let limit = pagination.limit();
-
-        let take_length = i32::min(edges_len, limit);
+        rows: Vec<(N, E)>,
+    ) -> (Connection<N, E>, Option<Cursor>) {
+        let limit = pagination.limit().max(0) as usize;
+        let has_more = rows.len() > limit;
+        let backward = pagination.last.is_some();
 
-        let edges = edges
+        let mut edges = rows
             .into_iter()
-            .take(take_length as usize)
-            .map(|edge| ConnectionEdge {
-                cursor: edge.cursor(),
-                node: edge,
+            .take(limit)
+            .map(|(node, data)| ConnectionEdge {
+                cursor: data.cursor(&node, order).with_scope(scope),
+                node,
+                data,
             })
             .collect::<Vec<_>>();
-        Ok(Self {
+        if backward {
+            edges.reverse();
+        }
+        // 需要探测的一侧：正向分页时为首条之前，反向分页时为末条之后；页为空时退回到传入的游标
+        let probe = if backward {
+            pagination
+                .before
+                .as_ref()
+                .map(|before| edges.last().map_or(before, |edge| &edge.cursor).clone())
+        } else {
+            pagination
+                .after
+                .as_ref()
+                .map(|after| edges.first().map_or(after, |edge| &edge.cursor).clone())
+        };
+        let connection = Self {
             page_info: PageInfo {
-                has_previous_page,
-                has_next_page,
-                start_cursor: edges.first().map(|edge| edge.cursor.clone()),
-                end_cursor: edges.last().map(|edge| edge.cursor.clone()),
+                has_previous_page: backward && has_more,
+                has_next_page: !backward && has_more,
+                start_cursor: edges.first().map(|edge| EncodedCursor::from(&edge.cursor)),
+                end_cursor: edges.last().map(|edge| EncodedCursor::from(&edge.cursor)),
             },
             edges,
             total_count,
-        })
+            fields: None,
+        };
+        (connection, probe)
     }
 
-    pub async fn new<'a, C, S, F1, F2>(
+    /// 与 `Connection::new` 相同，但 `loader` 需要同时返回每条边的附加数据
+    pub async fn with_edge_data<'a, C, S, T, F1, F2>(
         executor: &juniper::Executor<'_, '_, C, S>,
         pagination: Pagination,
+        filter: T,
+        order: E::Order,
         loader: F1,
         total_loader: F2,
-    ) -> juniper::FieldResult<Connection<N>>
+    ) -> juniper::FieldResult<Connection<N, E>>
     where
         S: juniper::ScalarValue + 'a,
         C: 'a,
-        F1: AsyncFnOnce(&Pagination) -> anyhow::Result<Vec<N>>,
-        F2: AsyncFnOnce() -> anyhow::Result<i32>,
+        F1: AsyncFnOnce(&Pagination, &T, &E::Order) -> crate::error::Result<Vec<(N, E)>> + Clone,
+        F2: AsyncFnOnce(&T) -> crate::error::Result<i32>,
+        T: std::fmt::Debug,
     {
         pagination.validate()?;
+        let scope = Cursor::scope_of(&filter);
+        pagination.validate_cursors(E::CONNECTION_TYPE_NAME, &scope, &order.signature())?;
         let children: juniper::LookAheadChildren<'_, S> = executor.look_ahead().children();
         let has_total_count_field = children
             .iter()
             .any(|sel| sel.field_original_name() == "totalCount");
-        let edges = loader(&pagination).await?;
+        let rows = loader.clone()(&pagination, &filter, &order)
+            .await
+            .map_err(IntoFieldError::into_field_error)?;
         let total_count = if has_total_count_field {
-            total_loader().await?
+            total_loader(&filter)
+                .await
+                .map_err(IntoFieldError::into_field_error)?
         } else {
             0
         };
-        Self::build_connection(pagination, total_count, edges)
+        let (mut connection, probe) =
+            Self::build_connection(&pagination, &scope, &order, total_count, rows);
+        // 只有传了 after/before 时另一侧才可能有数据，此时反方向取一条确认
+        if let Some(cursor) = probe {
+            let probe = if pagination.last.is_some() {
+                Pagination {
+                    first: Some(0),
+                    after: Some(cursor),
+                    ..Default::default()
+                }
+            } else {
+                Pagination {
+                    last: Some(0),
+                    before: Some(cursor),
+                    ..Default::default()
+                }
+            };
+            let exists = !loader(&probe, &filter, &order)
+                .await
+                .map_err(IntoFieldError::into_field_error)?
+                .is_empty();
+            if pagination.last.is_some() {
+                connection.page_info.has_next_page = exists;
+            } else {
+                connection.page_info.has_previous_page = exists;
+            }
+        }
+        Ok(connection)
+    }
+
+    /// 查询中选择了节点声明的附加字段时才调用 `loader` 加载，与 `totalCount` 一样按需计算
+    pub async fn load_fields<'a, C, S, F>(
+        mut self,
+        executor: &juniper::Executor<'_, '_, C, S>,
+        loader: F,
+    ) -> juniper::FieldResult<Self>
+    where
+        S: juniper::ScalarValue + 'a,
+        C: 'a,
+        F: AsyncFnOnce() -> crate::error::Result<E::ConnectionFields>,
+    {
+        if connection::fields_selected::<E::ConnectionFields, _, _>(executor) {
+            let fields = loader().await.map_err(IntoFieldError::into_field_error)?;
+            self.fields = Some(fields);
+        }
+        Ok(self)
+    }
+}
+
+impl<N> Connection<N>
+where
+    N: ConnectionNode,
+{
+    /// `filter` 会同时传给 `loader` 和 `total_loader`，保证 `totalCount` 与分页数据的筛选条件一致；
+    /// 它的摘要也会写进游标，游标在其他筛选条件下使用时返回 `CURSOR_CONNECTION_MISMATCH`
+    ///
+    /// `loader` 需要按 `Pagination::fetch_limit` 多取一条，用于判断是否还有更多数据；
+    /// 传入 `last` 时按倒序返回即可，边总是以正序输出。传了 `after`/`before` 时还会再调用一次
+    /// `loader` 探测另一侧是否有数据，因此要求 `loader` 可以克隆
+    pub async fn new<'a, C, S, T, F1, F2>(
+        executor: &juniper::Executor<'_, '_, C, S>,
+        pagination: Pagination,
+        filter: T,
+        order: N::Order,
+        loader: F1,
+        total_loader: F2,
+    ) -> juniper::FieldResult<Connection<N>>
+    where
+        S: juniper::ScalarValue + 'a,
+        C: 'a,
+        F1: AsyncFnOnce(&Pagination, &T, &N::Order) -> crate::error::Result<Vec<N>> + Clone,
+        F2: AsyncFnOnce(&T) -> crate::error::Result<i32>,
+        T: std::fmt::Debug,
+    {
+        Self::with_edge_data(
+            executor,
+            pagination,
+            filter,
+            order,
+            async |pagination, filter, order| {
+                let nodes = loader(pagination, filter, order).await?;
+                Ok(nodes.into_iter().map(|node| (node, ())).collect())
+            },
+            total_loader,
+        )
+        .await
     }
 }
@@ -1,7 +1,95 @@
-use super::Cursor;
+use super::{ConnectionFields, ConnectionOrder, Cursor};
+use crate::{
+    error,
+    graphql::{self, scalar},
+    models::{list::TodoList, operation::Operation, tag::Tag, todo::Todo},
+    utils::base64_url,
+};
+use juniper::{graphql_interface, GraphQLScalar};
 
+/// 可以分页查询的节点，通常通过 `#[derive(ConnectionNode)]` 实现
 pub trait ConnectionNode {
-    fn cursor(&self) -> Cursor;
+    type Order: ConnectionOrder;
+    /// 节点在连接上声明的附加字段，没有时为 `()`
+    type ConnectionFields: ConnectionFields;
+    fn cursor(&self, order: &Self::Order) -> Cursor;
     const CONNECTION_TYPE_NAME: &'static str;
     const EDGE_TYPE_NAME: &'static str;
-}
\ No newline at end of file
+    /// 页码分页时连接的类型名
+    const OFFSET_CONNECTION_TYPE_NAME: &'static str;
+}
+
+/// 可以通过全局 ID 重新获取的对象
+#[graphql_interface(for = [Todo, Tag, TodoList, Operation], context = graphql::Context, scalar = graphql::CustomScalarValue)]
+pub trait Node {
+    /// 全局唯一的对象 ID
+    fn id(&self) -> GlobalId;
+}
+
+/// Relay 全局对象 ID，对外编码为 `base64("类型名:行ID")`
+#[derive(Debug, Clone, Hash, Eq, PartialEq, GraphQLScalar)]
+#[graphql(name = "ID", with = global_id_scalar, parse_token(String))]
+pub struct GlobalId {
+    pub(crate) type_name: String,
+    pub(crate) id: scalar::ID,
+}
+
+mod global_id_scalar {
+    use super::*;
+    use juniper::{InputValue, ScalarValue, Value};
+
+    pub(super) fn to_output<S: ScalarValue>(v: &GlobalId) -> Value<S> {
+        Value::Scalar(String::from(v).into())
+    }
+    pub(super) fn from_input<S: ScalarValue>(v: &InputValue<S>) -> Result<GlobalId, String> {
+        v.as_string_value()
+            .ok_or_else(|| format!("Expected `String`, found: {v}"))
+            .and_then(|v| match GlobalId::try_from(v) {
+                Ok(id) => Ok(id),
+                Err(e) => Err(e.to_string()),
+            })
+    }
+}
+
+impl GlobalId {
+    pub fn new(type_name: impl Into<String>, id: scalar::ID) -> Self {
+        Self {
+            type_name: type_name.into(),
+            id,
+        }
+    }
+
+    /// 校验 ID 所属的类型并取出行 ID
+    pub fn to_local(&self, type_name: &str) -> error::Result<scalar::ID> {
+        if self.type_name == type_name {
+            return Ok(self.id);
+        }
+        Err(error::Error::Validation(format!(
+            "Expected an ID of type '{type_name}', found '{}'",
+            self.type_name
+        )))
+    }
+}
+
+impl From<&GlobalId> for String {
+    fn from(id: &GlobalId) -> Self {
+        base64_url::encode(format!("{}:{}", id.type_name, id.id).as_bytes())
+    }
+}
+
+impl<'a> TryFrom<&'a str> for GlobalId {
+    type Error = &'static str;
+    fn try_from(value: &'a str) -> Result<Self, Self::Error> {
+        static ERR_MSG: &str = "Invalid ID format";
+        let bytes = base64_url::decode(value).map_err(|_| ERR_MSG)?;
+        let str = std::str::from_utf8(&bytes).map_err(|_| ERR_MSG)?;
+        let (type_name, id) = str.split_once(":").ok_or(ERR_MSG)?;
+        if type_name.is_empty() {
+            return Err(ERR_MSG);
+        }
+        Ok(Self {
+            type_name: type_name.to_string(),
+            id: scalar::ID::from(id.parse::<i64>().map_err(|_| ERR_MSG)?),
+        })
+    }
+}
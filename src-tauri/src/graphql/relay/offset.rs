@@ -0,0 +1,295 @@
+use juniper::{
+    graphql_value,
+    macros::reflect::{BaseSubTypes, BaseType, Type, Types, WrappedType, WrappedValue},
+    marker::IsOutputType,
+    meta::MetaType,
+    Arguments, Context, ExecutionResult, Executor, FieldError, GraphQLInputObject, GraphQLType,
+    GraphQLValue, GraphQLValueAsync, IntoFieldError, Registry, ScalarValue,
+};
+
+use super::connection::{fields_selected, resolve_extra_field};
+use super::{ConnectionNode, Pagination};
+
+/// 按页码分页，适用于需要跳转到指定页的场景；数据变化时相邻两页之间可能出现重复或遗漏
+#[derive(Debug, Clone, GraphQLInputObject)]
+pub struct OffsetPagination {
+    /// 页码，从 1 开始
+    pub(crate) page: i32,
+    /// 每页条数
+    pub(crate) page_size: i32,
+}
+
+impl OffsetPagination {
+    pub fn validate(&self) -> Result<(), FieldError> {
+        if self.page < 1 {
+            return Err(FieldError::new(
+                "'page' argument must be at least 1",
+                graphql_value!({
+                    "code": "VALUE_OUT_OF_RANGE",
+                    "min": 1,
+                    "max": i32::MAX,
+                }),
+            ));
+        }
+        if self.page_size < 0 {
+            return Err(FieldError::new(
+                "'pageSize' argument must be positive number",
+                graphql_value!({
+                    "code": "VALUE_OUT_OF_RANGE",
+                    "min": 0,
+                    "max": i32::MAX,
+                }),
+            ));
+        }
+        Ok(())
+    }
+
+    /// 转换为游标分页使用的 `Pagination`，使两种分页方式共用同一个 loader
+    fn to_pagination(&self) -> Pagination {
+        Pagination {
+            first: Some(self.page_size),
+            offset: Some(i64::from(self.page - 1) * i64::from(self.page_size)),
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct OffsetConnection<N: ConnectionNode> {
+    pub(super) nodes: Vec<N>,
+    pub(super) total_count: i32,
+    pub(super) page_count: i32,
+    pub(super) current_page: i32,
+    pub(super) fields: Option<N::ConnectionFields>,
+}
+
+impl<N: ConnectionNode> OffsetConnection<N> {
+    /// 参数与 `Connection::new` 相同，可以直接复用同一组 loader
+    ///
+    /// `loader` 收到的 `Pagination` 只带 `first` 和 `offset`；`totalCount`、`pageCount`
+    /// 都没有被查询时不会调用 `total_loader`
+    pub async fn new<'a, C, S, T, F1, F2>(
+        executor: &juniper::Executor<'_, '_, C, S>,
+        pagination: OffsetPagination,
+        filter: T,
+        order: N::Order,
+        loader: F1,
+        total_loader: F2,
+    ) -> juniper::FieldResult<Self>
+    where
+        S: juniper::ScalarValue + 'a,
+        C: 'a,
+        F1: AsyncFnOnce(&Pagination, &T, &N::Order) -> crate::error::Result<Vec<N>>,
+        F2: AsyncFnOnce(&T) -> crate::error::Result<i32>,
+    {
+        pagination.validate()?;
+        let children: juniper::LookAheadChildren<'_, S> = executor.look_ahead().children();
+        let needs_total = children
+            .iter()
+            .any(|sel| matches!(sel.field_original_name(), "totalCount" | "pageCount"));
+        let mut nodes = loader(&pagination.to_pagination(), &filter, &order)
+            .await
+            .map_err(IntoFieldError::into_field_error)?;
+        nodes.truncate(pagination.page_size as usize);
+        let total_count = if needs_total {
+            total_loader(&filter)
+                .await
+                .map_err(IntoFieldError::into_field_error)?
+        } else {
+            0
+        };
+        Ok(Self {
+            nodes,
+            total_count,
+            page_count: page_count(total_count, pagination.page_size),
+            current_page: pagination.page,
+            fields: None,
+        })
+    }
+
+    /// 与 `Connection::load_fields` 相同，只有选择了附加字段时才调用 `loader`
+    pub async fn load_fields<'a, C, S, F>(
+        mut self,
+        executor: &juniper::Executor<'_, '_, C, S>,
+        loader: F,
+    ) -> juniper::FieldResult<Self>
+    where
+        S: juniper::ScalarValue + 'a,
+        C: 'a,
+        F: AsyncFnOnce() -> crate::error::Result<N::ConnectionFields>,
+    {
+        if fields_selected::<N::ConnectionFields, _, _>(executor) {
+            let fields = loader().await.map_err(IntoFieldError::into_field_error)?;
+            self.fields = Some(fields);
+        }
+        Ok(self)
+    }
+}
+
+impl<N, S> GraphQLType<S> for OffsetConnection<N>
+where
+    N: GraphQLType<S> + ConnectionNode,
+    N::Context: Context,
+    S: ScalarValue,
+{
+    fn name(_info: &<N as GraphQLValue<S>>::TypeInfo) -> Option<&str> {
+        Some(N::OFFSET_CONNECTION_TYPE_NAME)
+    }
+    fn meta<'r>(
+        info: &<N as GraphQLValue<S>>::TypeInfo,
+        registry: &mut Registry<'r, S>,
+    ) -> MetaType<'r, S>
+    where
+        S: 'r,
+    {
+        let mut fields = vec![
+            registry
+                .field::<&Vec<N>>("nodes", info)
+                .description("当前页的节点"),
+            registry
+                .field::<&i32>("totalCount", &())
+                .description("匹配当前筛选条件的总记录数，不受分页限制"),
+            registry
+                .field::<&i32>("pageCount", &())
+                .description("按当前每页条数计算的总页数"),
+            registry
+                .field::<&i32>("currentPage", &())
+                .description("当前页码，从 1 开始"),
+        ];
+        fields.extend(<N::ConnectionFields as super::ConnectionFields>::fields(
+            registry,
+        ));
+        registry
+            .build_object_type::<Self>(info, &fields)
+            .into_meta()
+    }
+}
+
+impl<N, S> GraphQLValue<S> for OffsetConnection<N>
+where
+    N: GraphQLType<S> + ConnectionNode,
+    N::Context: Context,
+    S: ScalarValue,
+{
+    type Context = N::Context;
+    type TypeInfo = <N as GraphQLValue<S>>::TypeInfo;
+
+    fn type_name<'i>(&self, info: &'i Self::TypeInfo) -> Option<&'i str> {
+        <Self as GraphQLType<S>>::name(info)
+    }
+    fn resolve_field(
+        &self,
+        info: &Self::TypeInfo,
+        field_name: &str,
+        _arguments: &Arguments<S>,
+        executor: &Executor<Self::Context, S>,
+    ) -> ExecutionResult<S> {
+        match field_name {
+            "nodes" => executor.resolve_with_ctx(info, &self.nodes),
+            "totalCount" => executor.resolve_with_ctx(&(), &self.total_count),
+            "pageCount" => executor.resolve_with_ctx(&(), &self.page_count),
+            "currentPage" => executor.resolve_with_ctx(&(), &self.current_page),
+            _ => resolve_extra_field(
+                self.fields.as_ref(),
+                "OffsetConnection",
+                field_name,
+                executor,
+            ),
+        }
+    }
+    fn concrete_type_name(&self, _context: &Self::Context, info: &Self::TypeInfo) -> String {
+        self.type_name(info)
+            .unwrap_or("OffsetConnection")
+            .to_string()
+    }
+}
+
+impl<N, S> GraphQLValueAsync<S> for OffsetConnection<N>
+where
+    N: GraphQLType<S> + GraphQLValueAsync<S> + ConnectionNode + Sync + Send,
+    N::TypeInfo: Sync,
+    N::Context: Context + Sync,
+    S: ScalarValue + Send + Sync,
+{
+    fn resolve_field_async<'a>(
+        &'a self,
+        info: &'a Self::TypeInfo,
+        field_name: &'a str,
+        arguments: &'a Arguments<S>,
+        executor: &'a Executor<Self::Context, S>,
+    ) -> juniper::BoxFuture<'a, ExecutionResult<S>> {
+        let f = async move {
+            match field_name {
+                "nodes" => executor.resolve_with_ctx_async(info, &self.nodes).await,
+                _ => self.resolve_field(info, field_name, arguments, executor),
+            }
+        };
+        use juniper::futures::future;
+        future::FutureExt::boxed(f)
+    }
+}
+
+impl<N, S> IsOutputType<S> for OffsetConnection<N>
+where
+    N: GraphQLType<S> + ConnectionNode,
+    S: ScalarValue,
+    <N as GraphQLValue<S>>::Context: Context,
+{
+}
+
+impl<N, S> BaseType<S> for OffsetConnection<N>
+where
+    N: GraphQLType<S> + ConnectionNode,
+    S: ScalarValue,
+{
+    const NAME: Type = N::OFFSET_CONNECTION_TYPE_NAME;
+}
+
+impl<N, S> BaseSubTypes<S> for OffsetConnection<N>
+where
+    N: GraphQLType<S> + ConnectionNode + BaseType<S>,
+    N::Context: Context,
+    S: ScalarValue,
+{
+    const NAMES: Types = &[<N as BaseType<S>>::NAME];
+}
+
+impl<N, S> WrappedType<S> for OffsetConnection<N>
+where
+    N: GraphQLType<S> + ConnectionNode,
+    S: ScalarValue,
+{
+    const VALUE: WrappedValue = 1;
+}
+
+/// 总页数，每页 0 条时为 0；在 i64 中计算，避免总数接近 `i32::MAX` 时溢出
+fn page_count(total_count: i32, page_size: i32) -> i32 {
+    match page_size {
+        0 => 0,
+        // 结果不会超过 `total_count`，转换回 i32 不会截断
+        size => {
+            let size = i64::from(size);
+            ((i64::from(total_count) + size - 1) / size) as i32
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn page_count_rounds_up() {
+        assert_eq!(page_count(0, 10), 0);
+        assert_eq!(page_count(10, 10), 1);
+        assert_eq!(page_count(11, 10), 2);
+        assert_eq!(page_count(5, 0), 0);
+    }
+
+    #[test]
+    fn page_count_does_not_overflow() {
+        assert_eq!(page_count(i32::MAX, i32::MAX), 1);
+        assert_eq!(page_count(i32::MAX, 2), i32::MAX / 2 + 1);
+        assert_eq!(page_count(i32::MAX, 1), i32::MAX);
+    }
+}
@@ -0,0 +1,55 @@
+use juniper::GraphQLEnum;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, GraphQLEnum)]
+pub enum OrderDirection {
+    /// 升序
+    #[default]
+    Asc,
+    /// 降序
+    Desc,
+}
+
+impl OrderDirection {
+    #[inline]
+    pub fn reverse(self) -> Self {
+        match self {
+            Self::Asc => Self::Desc,
+            Self::Desc => Self::Asc,
+        }
+    }
+
+    #[inline]
+    pub fn as_sql(self) -> &'static str {
+        match self {
+            Self::Asc => "ASC",
+            Self::Desc => "DESC",
+        }
+    }
+}
+
+/// 连接的排序方式，排序键之后总是隐式追加 `id ASC` 作为决胜键
+pub trait ConnectionOrder {
+    /// `keys` 可能返回的全部排序键，`#[derive(ConnectionNode)]` 在编译期检查它们都列在 `cursor` 中
+    const KEYS: &'static [&'static str];
+
+    /// 排序方式的规范化表示，会被写入游标，用于拒绝在其他排序下生成的游标
+    fn signature(&self) -> String;
+
+    /// 依次参与比较的排序键（节点的字段名），游标按此顺序记录各键的值
+    fn keys(&self) -> Vec<&'static str>;
+}
+
+impl<T: ConnectionOrder> ConnectionOrder for Vec<T> {
+    const KEYS: &'static [&'static str] = T::KEYS;
+
+    fn signature(&self) -> String {
+        self.iter()
+            .map(ConnectionOrder::signature)
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+
+    fn keys(&self) -> Vec<&'static str> {
+        self.iter().flat_map(ConnectionOrder::keys).collect()
+    }
+}
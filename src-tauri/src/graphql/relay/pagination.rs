@@ -1,16 +1,41 @@
-use juniper::{graphql_value, FieldError, GraphQLInputObject};
+use juniper::{graphql_value, FieldError, IntoFieldError};
 
-use super::Cursor;
+use super::{Cursor, EncodedCursor};
 
-#[derive(Debug, Default, GraphQLInputObject)]
+#[derive(Debug, Default)]
 pub struct Pagination {
     pub(crate) first: Option<i32>,
     pub(crate) after: Option<Cursor>,
     pub(crate) last: Option<i32>,
     pub(crate) before: Option<Cursor>,
+    /// 跳过的条数，只在页码分页（`OffsetPagination`）中使用，不能与游标同时出现
+    pub(crate) offset: Option<i64>,
 }
 
 impl Pagination {
+    /// 解码并校验客户端传入的游标，失败时按原因返回 `CURSOR_MALFORMED`、`CURSOR_TAMPERED` 等错误码
+    pub fn new(
+        first: Option<i32>,
+        after: Option<EncodedCursor>,
+        last: Option<i32>,
+        before: Option<EncodedCursor>,
+    ) -> Result<Self, FieldError> {
+        let decode = |cursor: Option<EncodedCursor>| {
+            cursor
+                .as_ref()
+                .map(Cursor::try_from)
+                .transpose()
+                .map_err(IntoFieldError::into_field_error)
+        };
+        Ok(Self {
+            first,
+            after: decode(after)?,
+            last,
+            before: decode(before)?,
+            offset: None,
+        })
+    }
+
     pub fn validate(&self) -> Result<(), FieldError> {
         match (
             (self.first, self.after.as_ref()),
@@ -56,9 +81,44 @@ impl Pagination {
             _ => Ok(()),
         }
     }
-    
+
+    /// 游标只能用于生成它的连接和筛选条件，并且只能在生成它时的排序方式下使用
+    pub fn validate_cursors(
+        &self,
+        connection: &str,
+        scope: &str,
+        signature: &str,
+    ) -> Result<(), FieldError> {
+        let cursors = [self.after.as_ref(), self.before.as_ref()];
+        for cursor in cursors.into_iter().flatten() {
+            cursor
+                .check_connection(connection, scope)
+                .map_err(IntoFieldError::into_field_error)?;
+        }
+        match cursors.into_iter().flatten().find(|c| c.order != signature) {
+            Some(_) => Err(FieldError::new(
+                "Cursor was created under a different ordering",
+                graphql_value!({
+                    "code": "CURSOR_ORDER_MISMATCH",
+                }),
+            )),
+            None => Ok(()),
+        }
+    }
+
     #[inline]
     pub fn limit(&self) -> i32 {
         self.first.or(self.last).unwrap_or(10)
     }
+
+    /// 查询时实际应取的条数，比 `limit` 多一条用于判断这一方向上是否还有数据
+    #[inline]
+    pub fn fetch_limit(&self) -> i32 {
+        self.limit().saturating_add(1)
+    }
+
+    #[inline]
+    pub fn offset(&self) -> i64 {
+        self.offset.unwrap_or(0)
+    }
 }
@@ -1,4 +1,3 @@
-use crate::utils::base64_url;
 use juniper::{graphql_scalar, GraphQLScalar, InputValue, ScalarValue, Value};
 use serde::{Deserialize, Serialize};
 use sqlx::{sqlite::SqliteTypeInfo, Database, Sqlite, Type};
@@ -93,32 +92,10 @@ impl Type<Sqlite> for Timestamp {
     }
 }
 
-#[derive(Debug, Copy, Hash, GraphQLScalar, Clone, Eq, PartialEq, Serialize, sqlx::Type)]
-#[graphql(with = id_scalar, parse_token(String))]
+/// 数据库中的行 ID，对外暴露时需要包装为 `relay::GlobalId`
+#[derive(Debug, Copy, Hash, Clone, Eq, PartialEq, Serialize, sqlx::Type)]
 pub struct ID(i64);
 
-mod id_scalar {
-    use super::*;
-
-    pub(super) fn to_output<S: ScalarValue>(v: &ID) -> Value<S> {
-        let value = base64_url::encode(&v.0.to_be_bytes());
-        Value::Scalar(value.into())
-    }
-    pub(super) fn from_input<S: ScalarValue>(v: &InputValue<S>) -> Result<ID, String> {
-        v.as_string_value()
-            .ok_or_else(|| format!("Expected `String`, found: {v}"))
-            .and_then(|s| {
-                match base64_url::decode(s)
-                    .map_err(|e| format!("{e}"))
-                    .and_then(|b| b.try_into().map_err(|_e| "Invalid byte length".to_string()))
-                    .map(i64::from_be_bytes)
-                {
-                    Ok(v) => Ok(ID(v)),
-                    Err(e) => Err(format!("Invalid ID, {e}")),
-                }
-            })
-    }
-}
 impl From<i64> for ID {
     fn from(value: i64) -> Self {
         Self(value)
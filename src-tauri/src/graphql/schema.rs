@@ -1,8 +1,29 @@
-use crate::models::todo::Todo;
+use std::pin::Pin;
+
+use crate::error::{Error, Result};
+use crate::events::TodoEvent;
+use crate::models::list::{TodoList, TodoListOrder};
+use crate::models::operation::{Operation, OperationOrder, OperationOrderField};
+use crate::models::payload::{
+    AddTodoInput, AddTodoPayload, ClearCompletedInput, ClearCompletedPayload, ClearRecurrenceInput,
+    CompleteTodoInput, CreateListInput, CreateTagInput, DeleteTagInput, DeleteTagPayload,
+    EditTodoInput, EmptyTrashInput, EmptyTrashPayload, HistoryPayload, ListPayload, MoveTodoInput,
+    MoveTodoUnderInput, RedoInput, RemoveTodoInput, RemoveTodoPayload, RenameTagInput,
+    RestoreAllInput, RestoreAllPayload, RestoreTodoInput, ScheduleTodoInput, SetRecurrenceInput,
+    TagPayload, TagTodoInput, ToggleAllInput, ToggleAllPayload, UndoInput, UntagTodoInput,
+    UpdateListInput, UpdateTodoPayload,
+};
+use crate::models::recurrence::Recurrence;
+use crate::models::search::{SearchMatch, SearchOrder};
+use crate::models::tag::{Tag, TagOrder};
+use crate::models::todo::{Todo, TodoFilter, TodoOrder};
 
 use super::context::Context;
 use super::{relay, scalar};
-use juniper::{graphql_object, EmptySubscription, Executor, FieldResult, RootNode};
+use juniper::{
+    futures::{future, Stream, StreamExt},
+    graphql_object, graphql_subscription, Executor, FieldResult, RootNode,
+};
 
 pub struct Query;
 
@@ -12,29 +33,227 @@ impl Query {
     pub fn greet(name: String) -> String {
         format!("Hello, {}! You've been greeted from Rust!", name)
     }
+    /// 通过全局 ID 获取任意实现了 `Node` 接口的对象，不存在时返回 null
+    pub async fn node(ctx: &Context, id: relay::GlobalId) -> Result<Option<relay::NodeValue>> {
+        let node = fetch_node(ctx, &id).await?;
+        Ok(node)
+    }
+    /// 批量获取对象，返回结果与 `ids` 的顺序一一对应
+    pub async fn nodes(
+        ctx: &Context,
+        ids: Vec<relay::GlobalId>,
+    ) -> Result<Vec<Option<relay::NodeValue>>> {
+        let nodes = future::try_join_all(ids.iter().map(|id| fetch_node(ctx, id))).await?;
+        Ok(nodes)
+    }
+    #[allow(clippy::too_many_arguments)]
     pub async fn list_todos(
         executor: &Executor<'_, '_, Context, scalar::CustomScalarValue>,
         ctx: &Context,
         first: Option<i32>,
-        after: Option<relay::Cursor>,
+        after: Option<relay::EncodedCursor>,
         last: Option<i32>,
-        before: Option<relay::Cursor>,
+        before: Option<relay::EncodedCursor>,
+        filter: Option<TodoFilter>,
+        order_by: Option<Vec<TodoOrder>>,
     ) -> FieldResult<relay::Connection<Todo>> {
-        let patination = relay::Pagination {
-            first,
-            after,
-            last,
-            before,
+        let pagination = relay::Pagination::new(first, after, last, before)?;
+        let filter = filter.unwrap_or_default();
+        let conn = relay::Connection::new(
+            executor,
+            pagination,
+            filter.clone(),
+            order_by.unwrap_or_default(),
+            async |pag, filter, order: &Vec<TodoOrder>| {
+                ctx.todo_repo.list_todos(pag, filter, order).await
+            },
+            async |filter| ctx.todo_repo.total(filter).await,
+        )
+        .await?
+        .load_fields(executor, async || ctx.todo_repo.counts(&filter).await)
+        .await?;
+        Ok(conn)
+    }
+    /// 与 `listTodos` 相同，但按页码分页
+    pub async fn list_todos_page(
+        executor: &Executor<'_, '_, Context, scalar::CustomScalarValue>,
+        ctx: &Context,
+        pagination: relay::OffsetPagination,
+        filter: Option<TodoFilter>,
+        order_by: Option<Vec<TodoOrder>>,
+    ) -> FieldResult<relay::OffsetConnection<Todo>> {
+        let filter = filter.unwrap_or_default();
+        let conn = relay::OffsetConnection::new(
+            executor,
+            pagination,
+            filter.clone(),
+            order_by.unwrap_or_default(),
+            async |pag, filter, order: &Vec<TodoOrder>| {
+                ctx.todo_repo.list_todos(pag, filter, order).await
+            },
+            async |filter| ctx.todo_repo.total(filter).await,
+        )
+        .await?
+        .load_fields(executor, async || ctx.todo_repo.counts(&filter).await)
+        .await?;
+        Ok(conn)
+    }
+    /// 回收站中的事项，参数与 `listTodos` 相同；超过保留期的事项会被自动彻底删除
+    #[allow(clippy::too_many_arguments)]
+    pub async fn trash(
+        executor: &Executor<'_, '_, Context, scalar::CustomScalarValue>,
+        ctx: &Context,
+        first: Option<i32>,
+        after: Option<relay::EncodedCursor>,
+        last: Option<i32>,
+        before: Option<relay::EncodedCursor>,
+        filter: Option<TodoFilter>,
+        order_by: Option<Vec<TodoOrder>>,
+    ) -> FieldResult<relay::Connection<Todo>> {
+        let pagination = relay::Pagination::new(first, after, last, before)?;
+        let filter = TodoFilter {
+            trashed: true,
+            ..filter.unwrap_or_default()
         };
         let conn = relay::Connection::new(
             executor,
-            patination,
-            async |pag| ctx.todo_repo.list_todos(&pag).await,
-            async || ctx.todo_repo.total().await,
+            pagination,
+            filter.clone(),
+            order_by.unwrap_or_default(),
+            async |pag, filter, order: &Vec<TodoOrder>| {
+                ctx.todo_repo.list_todos(pag, filter, order).await
+            },
+            async |filter| ctx.todo_repo.total(filter).await,
         )
+        .await?
+        .load_fields(executor, async || ctx.todo_repo.counts(&filter).await)
         .await?;
         Ok(conn)
     }
+    /// 全文搜索待办事项的描述，结果按相关度排序，相关度相同时按 ID 排序
+    ///
+    /// 游标记录相关度和 ID，同一份索引上翻页不会遗漏或重复结果；相关度依赖整个索引的统计，
+    /// 翻页期间有事项被添加、修改或删除时，后续页可能遗漏或重复结果，需要时重新搜索
+    pub async fn search_todos(
+        executor: &Executor<'_, '_, Context, scalar::CustomScalarValue>,
+        ctx: &Context,
+        query: String,
+        first: Option<i32>,
+        after: Option<relay::EncodedCursor>,
+        last: Option<i32>,
+        before: Option<relay::EncodedCursor>,
+    ) -> FieldResult<relay::Connection<Todo, SearchMatch>> {
+        let pagination = relay::Pagination::new(first, after, last, before)?;
+        let conn = relay::Connection::with_edge_data(
+            executor,
+            pagination,
+            query,
+            SearchOrder,
+            async |pag, query: &String, _| ctx.todo_repo.search_todos(pag, query).await,
+            async |query: &String| ctx.todo_repo.search_total(query).await,
+        )
+        .await?;
+        Ok(conn)
+    }
+    /// 清单，默认只返回未归档的清单，`archived` 传 null 时返回全部
+    #[allow(clippy::too_many_arguments)]
+    pub async fn lists(
+        executor: &Executor<'_, '_, Context, scalar::CustomScalarValue>,
+        ctx: &Context,
+        first: Option<i32>,
+        after: Option<relay::EncodedCursor>,
+        last: Option<i32>,
+        before: Option<relay::EncodedCursor>,
+        #[graphql(default = Some(false))] archived: Option<bool>,
+        order_by: Option<Vec<TodoListOrder>>,
+    ) -> FieldResult<relay::Connection<TodoList>> {
+        let pagination = relay::Pagination::new(first, after, last, before)?;
+        let conn = relay::Connection::new(
+            executor,
+            pagination,
+            archived,
+            order_by.unwrap_or_default(),
+            async |pag, archived, order: &Vec<TodoListOrder>| {
+                ctx.list_repo.list_lists(pag, *archived, order).await
+            },
+            async |archived| ctx.list_repo.total(*archived).await,
+        )
+        .await?;
+        Ok(conn)
+    }
+    /// 全部标签及其使用次数
+    pub async fn tags(
+        executor: &Executor<'_, '_, Context, scalar::CustomScalarValue>,
+        ctx: &Context,
+        first: Option<i32>,
+        after: Option<relay::EncodedCursor>,
+        last: Option<i32>,
+        before: Option<relay::EncodedCursor>,
+        order_by: Option<Vec<TagOrder>>,
+    ) -> FieldResult<relay::Connection<Tag>> {
+        let pagination = relay::Pagination::new(first, after, last, before)?;
+        let conn = relay::Connection::new(
+            executor,
+            pagination,
+            None,
+            order_by.unwrap_or_default(),
+            async |pag, todo, order: &Vec<TagOrder>| {
+                ctx.tag_repo.list_tags(pag, *todo, order).await
+            },
+            async |todo| ctx.tag_repo.total(*todo).await,
+        )
+        .await?;
+        Ok(conn)
+    }
+    /// 操作日志，默认最新的操作在前；只保留最近的操作，`redo` 之外的新操作会清除已撤销的记录
+    pub async fn history(
+        executor: &Executor<'_, '_, Context, scalar::CustomScalarValue>,
+        ctx: &Context,
+        first: Option<i32>,
+        after: Option<relay::EncodedCursor>,
+        last: Option<i32>,
+        before: Option<relay::EncodedCursor>,
+        order_by: Option<Vec<OperationOrder>>,
+    ) -> FieldResult<relay::Connection<Operation>> {
+        let pagination = relay::Pagination::new(first, after, last, before)?;
+        let order_by = order_by.unwrap_or_else(|| {
+            vec![OperationOrder {
+                field: OperationOrderField::CreatedAt,
+                direction: relay::OrderDirection::Desc,
+            }]
+        });
+        let conn = relay::Connection::new(
+            executor,
+            pagination,
+            (),
+            order_by,
+            async |pag, _, order: &Vec<OperationOrder>| {
+                ctx.operation_repo.list_operations(pag, order).await
+            },
+            async |_| ctx.operation_repo.total().await,
+        )
+        .await?;
+        Ok(conn)
+    }
+}
+
+async fn fetch_node(ctx: &Context, id: &relay::GlobalId) -> Result<Option<relay::NodeValue>> {
+    let node = match id.type_name.as_str() {
+        Todo::TYPE_NAME => ctx.todo_loader.load(id.id).await?.map(Into::into),
+        Tag::TYPE_NAME => ctx.tag_loader.load(id.id).await?.map(Into::into),
+        TodoList::TYPE_NAME => ctx.list_loader.load(id.id).await?.map(Into::into),
+        Operation::TYPE_NAME => ctx.operation_loader.load(id.id).await?.map(Into::into),
+        _ => None,
+    };
+    Ok(node)
+}
+
+/// 未指定清单的写操作作用于 Inbox
+fn list_id_or_inbox(id: Option<&relay::GlobalId>) -> Result<scalar::ID> {
+    match id {
+        Some(id) => id.to_local(TodoList::TYPE_NAME),
+        None => Ok(scalar::ID::from(TodoList::INBOX_ID)),
+    }
 }
 
 pub struct Mutation;
@@ -44,47 +263,358 @@ impl Mutation {
     pub fn add(a: i32, b: i32) -> i32 {
         a + b
     }
-    pub async fn add_todo(ctx: &Context, description: String) -> FieldResult<scalar::ID> {
-        let id = ctx.todo_repo.add_todo(description).await?;
-        Ok(id)
+    pub async fn add_todo(ctx: &Context, input: AddTodoInput) -> Result<AddTodoPayload> {
+        let list_id = list_id_or_inbox(input.list_id.as_ref())?;
+        let todo = ctx.todo_repo.add_todo(list_id, input.description).await?;
+        ctx.todo_loader.prime(todo.id, todo.clone());
+        Ok(AddTodoPayload {
+            todo,
+            client_mutation_id: input.client_mutation_id,
+        })
+    }
+    pub async fn complete_todo(
+        ctx: &Context,
+        input: CompleteTodoInput,
+    ) -> Result<UpdateTodoPayload> {
+        let id = input.id.to_local(Todo::TYPE_NAME)?;
+        let todo = ctx
+            .todo_repo
+            .complete_todo(id, input.done, input.include_descendants)
+            .await?;
+        ctx.todo_loader.prime(todo.id, todo.clone());
+        Ok(UpdateTodoPayload {
+            todo,
+            client_mutation_id: input.client_mutation_id,
+        })
+    }
+    /// 移入回收站，可以通过 `restoreTodo` 恢复
+    pub async fn remove_todo(ctx: &Context, input: RemoveTodoInput) -> Result<RemoveTodoPayload> {
+        let id = input.id.to_local(Todo::TYPE_NAME)?;
+        let id = ctx.todo_repo.remove_todo(id).await?;
+        ctx.todo_loader.clear(&id);
+        Ok(RemoveTodoPayload {
+            deleted_todo_id: relay::GlobalId::new(Todo::TYPE_NAME, id),
+            client_mutation_id: input.client_mutation_id,
+        })
+    }
+    pub async fn edit_todo(ctx: &Context, input: EditTodoInput) -> Result<UpdateTodoPayload> {
+        let id = input.id.to_local(Todo::TYPE_NAME)?;
+        let todo = ctx.todo_repo.edit_todo(id, input.description).await?;
+        ctx.todo_loader.prime(todo.id, todo.clone());
+        Ok(UpdateTodoPayload {
+            todo,
+            client_mutation_id: input.client_mutation_id,
+        })
+    }
+    pub async fn schedule_todo(
+        ctx: &Context,
+        input: ScheduleTodoInput,
+    ) -> Result<UpdateTodoPayload> {
+        let id = input.id.to_local(Todo::TYPE_NAME)?;
+        let todo = ctx
+            .todo_repo
+            .schedule_todo(id, input.due_at, input.remind_at)
+            .await?;
+        ctx.todo_loader.prime(todo.id, todo.clone());
+        Ok(UpdateTodoPayload {
+            todo,
+            client_mutation_id: input.client_mutation_id,
+        })
+    }
+    pub async fn set_recurrence(
+        ctx: &Context,
+        input: SetRecurrenceInput,
+    ) -> Result<UpdateTodoPayload> {
+        let id = input.id.to_local(Todo::TYPE_NAME)?;
+        let rule = input.rule.parse::<Recurrence>()?;
+        let todo = ctx.todo_repo.set_recurrence(id, Some(rule)).await?;
+        ctx.todo_loader.prime(todo.id, todo.clone());
+        Ok(UpdateTodoPayload {
+            todo,
+            client_mutation_id: input.client_mutation_id,
+        })
+    }
+    pub async fn clear_recurrence(
+        ctx: &Context,
+        input: ClearRecurrenceInput,
+    ) -> Result<UpdateTodoPayload> {
+        let id = input.id.to_local(Todo::TYPE_NAME)?;
+        let todo = ctx.todo_repo.set_recurrence(id, None).await?;
+        ctx.todo_loader.prime(todo.id, todo.clone());
+        Ok(UpdateTodoPayload {
+            todo,
+            client_mutation_id: input.client_mutation_id,
+        })
+    }
+    /// 调整手动排序，按 `position` 排序的列表会反映新的顺序
+    pub async fn move_todo(ctx: &Context, input: MoveTodoInput) -> Result<UpdateTodoPayload> {
+        let id = input.id.to_local(Todo::TYPE_NAME)?;
+        let (anchor, before) = match (input.before_id, input.after_id) {
+            (Some(anchor), None) => (anchor, true),
+            (None, Some(anchor)) => (anchor, false),
+            _ => {
+                return Err(Error::Validation(
+                    "Exactly one of 'beforeId' and 'afterId' must be provided".to_string(),
+                ))
+            }
+        };
+        let anchor = anchor.to_local(Todo::TYPE_NAME)?;
+        let todo = ctx.todo_repo.move_todo(id, anchor, before).await?;
+        ctx.todo_loader.prime(todo.id, todo.clone());
+        Ok(UpdateTodoPayload {
+            todo,
+            client_mutation_id: input.client_mutation_id,
+        })
+    }
+    pub async fn move_todo_under(
+        ctx: &Context,
+        input: MoveTodoUnderInput,
+    ) -> Result<UpdateTodoPayload> {
+        let id = input.id.to_local(Todo::TYPE_NAME)?;
+        let parent = input
+            .parent_id
+            .map(|it| it.to_local(Todo::TYPE_NAME))
+            .transpose()?;
+        let todo = ctx.todo_repo.move_todo_under(id, parent).await?;
+        ctx.todo_loader.prime(todo.id, todo.clone());
+        Ok(UpdateTodoPayload {
+            todo,
+            client_mutation_id: input.client_mutation_id,
+        })
+    }
+    pub async fn toggle_all(ctx: &Context, input: ToggleAllInput) -> Result<ToggleAllPayload> {
+        let list_id = list_id_or_inbox(input.list_id.as_ref())?;
+        let updated_todos = ctx.todo_repo.toggle_all(list_id, input.done).await?;
+        for todo in updated_todos.iter() {
+            ctx.todo_loader.prime(todo.id, todo.clone());
+        }
+        Ok(ToggleAllPayload {
+            updated_todos,
+            client_mutation_id: input.client_mutation_id,
+        })
+    }
+    pub async fn tag_todo(ctx: &Context, input: TagTodoInput) -> Result<UpdateTodoPayload> {
+        let id = input.todo_id.to_local(Todo::TYPE_NAME)?;
+        let tag_id = input.tag_id.to_local(Tag::TYPE_NAME)?;
+        let todo = ctx.todo_repo.tag_todo(id, tag_id).await?;
+        ctx.todo_loader.prime(todo.id, todo.clone());
+        ctx.tag_loader.clear(&tag_id);
+        Ok(UpdateTodoPayload {
+            todo,
+            client_mutation_id: input.client_mutation_id,
+        })
+    }
+    pub async fn untag_todo(ctx: &Context, input: UntagTodoInput) -> Result<UpdateTodoPayload> {
+        let id = input.todo_id.to_local(Todo::TYPE_NAME)?;
+        let tag_id = input.tag_id.to_local(Tag::TYPE_NAME)?;
+        let todo = ctx.todo_repo.untag_todo(id, tag_id).await?;
+        ctx.todo_loader.prime(todo.id, todo.clone());
+        ctx.tag_loader.clear(&tag_id);
+        Ok(UpdateTodoPayload {
+            todo,
+            client_mutation_id: input.client_mutation_id,
+        })
+    }
+    pub async fn create_list(ctx: &Context, input: CreateListInput) -> Result<ListPayload> {
+        let list = ctx.list_repo.create_list(input.name, input.color).await?;
+        ctx.list_loader.prime(list.id, list.clone());
+        Ok(ListPayload {
+            list,
+            client_mutation_id: input.client_mutation_id,
+        })
+    }
+    /// 修改清单的名称、颜色或归档状态，归档不影响其中的事项
+    pub async fn update_list(ctx: &Context, input: UpdateListInput) -> Result<ListPayload> {
+        let id = input.id.to_local(TodoList::TYPE_NAME)?;
+        let list = ctx
+            .list_repo
+            .update_list(id, input.name, input.color, input.archived)
+            .await?;
+        ctx.list_loader.prime(list.id, list.clone());
+        Ok(ListPayload {
+            list,
+            client_mutation_id: input.client_mutation_id,
+        })
+    }
+    pub async fn create_tag(ctx: &Context, input: CreateTagInput) -> Result<TagPayload> {
+        let tag = ctx.tag_repo.create_tag(input.name).await?;
+        ctx.tag_loader.prime(tag.id, tag.clone());
+        Ok(TagPayload {
+            tag,
+            client_mutation_id: input.client_mutation_id,
+        })
     }
-    pub async fn complete_todo(ctx: &Context, id: scalar::ID, done: bool) -> FieldResult<bool> {
-        let suc = ctx.todo_repo.complete_todo(id, done).await?;
-        Ok(suc)
+    pub async fn rename_tag(ctx: &Context, input: RenameTagInput) -> Result<TagPayload> {
+        let id = input.id.to_local(Tag::TYPE_NAME)?;
+        let tag = ctx.tag_repo.rename_tag(id, input.name).await?;
+        ctx.tag_loader.prime(tag.id, tag.clone());
+        Ok(TagPayload {
+            tag,
+            client_mutation_id: input.client_mutation_id,
+        })
     }
-    pub async fn remove_todo(ctx: &Context, id: scalar::ID) -> FieldResult<bool> {
-        let suc = ctx.todo_repo.remove_todo(id).await?;
-        Ok(suc)
+    /// 删除标签，事项上的该标签会一并移除
+    pub async fn delete_tag(ctx: &Context, input: DeleteTagInput) -> Result<DeleteTagPayload> {
+        let id = input.id.to_local(Tag::TYPE_NAME)?;
+        let id = ctx.tag_repo.delete_tag(id).await?;
+        ctx.tag_loader.clear(&id);
+        Ok(DeleteTagPayload {
+            deleted_tag_id: relay::GlobalId::new(Tag::TYPE_NAME, id),
+            client_mutation_id: input.client_mutation_id,
+        })
     }
-    pub async fn edit_todo(
+    /// 已完成的事项移入回收站，可以通过 `restoreAll` 恢复
+    pub async fn clear_completed(
         ctx: &Context,
-        id: scalar::ID,
-        description: String,
-    ) -> FieldResult<bool> {
-        let suc = ctx.todo_repo.edit_todo(id, description).await?;
-        Ok(suc)
+        input: Option<ClearCompletedInput>,
+    ) -> Result<ClearCompletedPayload> {
+        let input = input.unwrap_or_default();
+        let list_id = list_id_or_inbox(input.list_id.as_ref())?;
+        let ids = ctx.todo_repo.clear_completed(list_id).await?;
+        for id in ids.iter() {
+            ctx.todo_loader.clear(id);
+        }
+        Ok(ClearCompletedPayload {
+            deleted_todo_ids: ids
+                .into_iter()
+                .map(|id| relay::GlobalId::new(Todo::TYPE_NAME, id))
+                .collect(),
+            client_mutation_id: input.client_mutation_id,
+        })
+    }
+    pub async fn restore_todo(ctx: &Context, input: RestoreTodoInput) -> Result<UpdateTodoPayload> {
+        let id = input.id.to_local(Todo::TYPE_NAME)?;
+        let todo = ctx.todo_repo.restore_todo(id).await?;
+        ctx.todo_loader.prime(todo.id, todo.clone());
+        Ok(UpdateTodoPayload {
+            todo,
+            client_mutation_id: input.client_mutation_id,
+        })
+    }
+    pub async fn restore_all(
+        ctx: &Context,
+        input: Option<RestoreAllInput>,
+    ) -> Result<RestoreAllPayload> {
+        let input = input.unwrap_or_default();
+        let list_id = input
+            .list_id
+            .map(|it| it.to_local(TodoList::TYPE_NAME))
+            .transpose()?;
+        let restored_todos = ctx.todo_repo.restore_all(list_id).await?;
+        for todo in restored_todos.iter() {
+            ctx.todo_loader.prime(todo.id, todo.clone());
+        }
+        Ok(RestoreAllPayload {
+            restored_todos,
+            client_mutation_id: input.client_mutation_id,
+        })
+    }
+    /// 彻底删除回收站中的所有事项
+    pub async fn empty_trash(
+        ctx: &Context,
+        input: Option<EmptyTrashInput>,
+    ) -> Result<EmptyTrashPayload> {
+        let input = input.unwrap_or_default();
+        let ids = ctx.todo_repo.empty_trash().await?;
+        for id in ids.iter() {
+            ctx.todo_loader.clear(id);
+        }
+        Ok(EmptyTrashPayload {
+            deleted_todo_ids: ids
+                .into_iter()
+                .map(|id| relay::GlobalId::new(Todo::TYPE_NAME, id))
+                .collect(),
+            client_mutation_id: input.client_mutation_id,
+        })
+    }
+    /// 撤销最近一次添加、编辑、完成、删除、全部切换或清除已完成的操作，批量操作整体撤销
+    pub async fn undo(ctx: &Context, input: Option<UndoInput>) -> Result<HistoryPayload> {
+        let input = input.unwrap_or_default();
+        let replayed = ctx.todo_repo.undo().await?;
+        Ok(history_payload(ctx, replayed, input.client_mutation_id))
     }
-    pub async fn toggle_all(ctx: &Context, done: bool) -> FieldResult<bool> {
-        let suc = ctx.todo_repo.toggle_all(done).await?;
-        Ok(suc)
+    /// 重做最早一次被撤销的操作
+    pub async fn redo(ctx: &Context, input: Option<RedoInput>) -> Result<HistoryPayload> {
+        let input = input.unwrap_or_default();
+        let replayed = ctx.todo_repo.redo().await?;
+        Ok(history_payload(ctx, replayed, input.client_mutation_id))
     }
-    pub async fn clear_completed(ctx: &Context) -> FieldResult<bool> {
-        let suc = ctx.todo_repo.clear_completed().await?;
-        Ok(suc)
+}
+
+fn history_payload(
+    ctx: &Context,
+    replayed: Option<(Operation, Vec<Todo>)>,
+    client_mutation_id: Option<String>,
+) -> HistoryPayload {
+    let (operation, todos) = match replayed {
+        Some((operation, todos)) => (Some(operation), todos),
+        None => (None, Vec::new()),
+    };
+    for todo in todos.iter() {
+        if todo.deleted_at.is_some() {
+            ctx.todo_loader.clear(&todo.id);
+        } else {
+            ctx.todo_loader.prime(todo.id, todo.clone());
+        }
+    }
+    HistoryPayload {
+        operation,
+        todos,
+        client_mutation_id,
     }
 }
 
-pub type Schema =
-    RootNode<'static, Query, Mutation, EmptySubscription<Context>, scalar::CustomScalarValue>;
+type EventStream<T> = Pin<Box<dyn Stream<Item = FieldResult<T, scalar::CustomScalarValue>> + Send>>;
+
+pub struct Subscription;
+#[graphql_subscription]
+#[graphql(context = Context, scalar = scalar::CustomScalarValue)]
+impl Subscription {
+    /// 新增待办事项时推送
+    pub async fn todo_added(ctx: &Context) -> EventStream<Todo> {
+        ctx.subscribe(|event| match event {
+            TodoEvent::Added(todo) => Some(Ok(todo)),
+            _ => None,
+        })
+        .boxed()
+    }
+    /// 待办事项的描述或完成状态变化时推送
+    pub async fn todo_updated(ctx: &Context) -> EventStream<Todo> {
+        ctx.subscribe(|event| match event {
+            TodoEvent::Updated(todo) => Some(Ok(todo)),
+            _ => None,
+        })
+        .boxed()
+    }
+    /// 删除单个待办事项时推送被删除的 ID
+    pub async fn todo_removed(ctx: &Context) -> EventStream<relay::GlobalId> {
+        ctx.subscribe(|event| match event {
+            TodoEvent::Removed(id) => Some(Ok(relay::GlobalId::new(Todo::TYPE_NAME, id))),
+            _ => None,
+        })
+        .boxed()
+    }
+    /// 清除已完成事项，或彻底删除回收站中的事项时推送被删除的全部 ID
+    pub async fn todos_cleared(ctx: &Context) -> EventStream<Vec<relay::GlobalId>> {
+        ctx.subscribe(|event| match event {
+            TodoEvent::Cleared(ids) => Some(Ok(ids
+                .into_iter()
+                .map(|id| relay::GlobalId::new(Todo::TYPE_NAME, id))
+                .collect())),
+            _ => None,
+        })
+        .boxed()
+    }
+}
+
+pub type Schema = RootNode<'static, Query, Mutation, Subscription, scalar::CustomScalarValue>;
 
 pub fn create_schema() -> Schema {
-    let schema = Schema::new_with_scalar_value(Query, Mutation, EmptySubscription::new());
-    #[cfg(debug_assertions)]
-    {
-        // 每次启动时输出 schema 到文件
-        let root_dir = std::env::current_dir().unwrap();
-        let path = root_dir.join("graphql.schema");
-        std::fs::write(&path, schema.as_sdl()).unwrap();
-    }
-    schema
+    Schema::new_with_scalar_value(Query, Mutation, Subscription)
+}
+
+/// 将 schema 输出到当前目录下的 `graphql.schema`，调试构建每次启动时调用
+pub fn write_sdl(schema: &Schema) -> std::io::Result<()> {
+    let path = std::env::current_dir()?.join("graphql.schema");
+    std::fs::write(path, schema.as_sdl())
 }
@@ -1,25 +1,58 @@
-use dotenvy::dotenv;
-use tauri::Manager;
+use tauri::{Emitter, Manager};
 
 mod commands;
+mod database;
+mod error;
+mod events;
 mod graphql;
 mod models;
+mod reminders;
 mod repositories;
+mod settings;
 mod state;
+mod trash;
 mod utils;
 
+/// 签名分页游标的密钥文件名，位于应用数据目录下
+const CURSOR_KEY_FILE: &str = "cursor.key";
+
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub async fn run() -> anyhow::Result<()> {
-    dotenv().unwrap();
-    let pool = sqlx::SqlitePool::connect(&std::env::var("DATABASE_URL")?).await?;
-    tauri::Builder::default()
+    let app = tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
-        .setup(|app| {
-            app.manage(state::build_app_state(pool));
-            Ok(())
-        })
-        .invoke_handler(tauri::generate_handler![commands::graphql::graphql])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .invoke_handler(tauri::generate_handler![
+            commands::graphql::graphql,
+            commands::graphql::graphql_subscribe,
+            commands::graphql::graphql_unsubscribe
+        ])
+        .build(tauri::generate_context!())?;
+    // 数据库路径依赖应用标识，需要先构建 App 才能解析；事件循环启动前不会处理任何命令
+    let data_dir = database::data_dir(app.handle())?;
+    let settings = settings::Settings::load(&data_dir)?;
+    let pool = database::connect(&data_dir).await?;
+    let mut cursor_key =
+        graphql::relay::CursorKey::load_or_create(&data_dir.join(CURSOR_KEY_FILE))?;
+    if let Some(ttl) = settings.cursor_ttl() {
+        cursor_key = cursor_key.with_ttl(ttl);
+    }
+    cursor_key.install()?;
+    let state = state::build_app_state(pool);
+    let handle = app.handle().clone();
+    tauri::async_runtime::spawn(reminders::run_scheduler(
+        state.pool.clone(),
+        state.events.clone(),
+        move |reminder| {
+            if let Err(e) = handle.emit(reminders::REMINDER_EVENT, reminder) {
+                eprintln!("Failed to emit reminder: {e}");
+            }
+        },
+    ));
+    tauri::async_runtime::spawn(trash::run_purge(
+        state.pool.clone(),
+        state.events.clone(),
+        settings.purge_policy(),
+    ));
+    app.manage(state);
+    app.run(|_, _| {});
     Ok(())
 }
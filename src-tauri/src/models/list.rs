@@ -0,0 +1,119 @@
+use crate::graphql::{self, relay, scalar};
+use crate::models::todo::{Todo, TodoFilter, TodoOrder};
+use juniper::{graphql_object, Executor, FieldResult, GraphQLEnum, GraphQLInputObject};
+use serde::Serialize;
+use tauri_graphql_demo_macros::ConnectionNode;
+
+/// 待办清单（项目），每个事项属于且只属于一个清单
+#[derive(Debug, Clone, Serialize, sqlx::FromRow, ConnectionNode)]
+#[connection(cursor = "id, name, created_at")]
+pub struct TodoList {
+    pub(crate) id: scalar::ID,
+    pub(crate) name: String,
+    pub(crate) color: Option<String>,
+    pub(crate) archived: bool,
+    pub(crate) created_at: scalar::Timestamp,
+}
+
+impl TodoList {
+    pub const TYPE_NAME: &'static str = "TodoList";
+    /// 迁移中创建的默认清单 Inbox，未指定清单时使用
+    pub const INBOX_ID: i64 = 1;
+}
+
+#[graphql_object(
+    context = graphql::Context,
+    scalar = graphql::CustomScalarValue,
+    impl = relay::NodeValue
+)]
+impl TodoList {
+    pub fn id(&self) -> relay::GlobalId {
+        relay::GlobalId::new(Self::TYPE_NAME, self.id)
+    }
+    pub fn name(&self) -> &String {
+        &self.name
+    }
+    /// 显示颜色，格式为 `#RRGGBB`
+    pub fn color(&self) -> Option<&str> {
+        self.color.as_deref()
+    }
+    /// 已归档的清单默认不出现在 `lists` 中
+    pub fn archived(&self) -> bool {
+        self.archived
+    }
+    pub fn created_at(&self) -> &scalar::Timestamp {
+        &self.created_at
+    }
+    /// 清单中的事项，参数与 `listTodos` 相同，`filter.listId` 会被忽略
+    #[allow(clippy::too_many_arguments)]
+    pub async fn todos(
+        &self,
+        executor: &Executor<'_, '_, graphql::Context, graphql::CustomScalarValue>,
+        ctx: &graphql::Context,
+        first: Option<i32>,
+        after: Option<relay::EncodedCursor>,
+        last: Option<i32>,
+        before: Option<relay::EncodedCursor>,
+        filter: Option<TodoFilter>,
+        order_by: Option<Vec<TodoOrder>>,
+    ) -> FieldResult<relay::Connection<Todo>> {
+        let pagination = relay::Pagination::new(first, after, last, before)?;
+        let filter = TodoFilter {
+            list_id: Some(self.id()),
+            ..filter.unwrap_or_default()
+        };
+        let conn = relay::Connection::new(
+            executor,
+            pagination,
+            filter.clone(),
+            order_by.unwrap_or_default(),
+            async |pag, filter, order: &Vec<TodoOrder>| {
+                ctx.todo_repo.list_todos(pag, filter, order).await
+            },
+            async |filter| ctx.todo_repo.total(filter).await,
+        )
+        .await?
+        .load_fields(executor, async || ctx.todo_repo.counts(&filter).await)
+        .await?;
+        Ok(conn)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, GraphQLEnum)]
+pub enum TodoListOrderField {
+    Name,
+    CreatedAt,
+}
+
+impl TodoListOrderField {
+    pub const fn column(self) -> &'static str {
+        match self {
+            Self::Name => "name",
+            Self::CreatedAt => "created_at",
+        }
+    }
+}
+
+/// 清单连接的排序键，多个排序键按顺序依次比较
+#[derive(Debug, Clone, GraphQLInputObject)]
+#[graphql(scalar = graphql::CustomScalarValue)]
+pub struct TodoListOrder {
+    pub(crate) field: TodoListOrderField,
+    #[graphql(default)]
+    pub(crate) direction: relay::OrderDirection,
+}
+
+impl relay::ConnectionOrder for TodoListOrder {
+    const KEYS: &'static [&'static str] = &[
+        TodoListOrderField::Name.column(),
+        TodoListOrderField::CreatedAt.column(),
+    ];
+
+    fn signature(&self) -> String {
+        format!("{}:{}", self.field.column(), self.direction.as_sql())
+    }
+
+    fn keys(&self) -> Vec<&'static str> {
+        vec![self.field.column()]
+    }
+}
@@ -1 +1,7 @@
-pub mod todo;
\ No newline at end of file
+pub mod list;
+pub mod operation;
+pub mod payload;
+pub mod recurrence;
+pub mod search;
+pub mod tag;
+pub mod todo;
@@ -0,0 +1,149 @@
+use crate::error::Result;
+use crate::graphql::{self, relay, scalar};
+use crate::models::todo::Todo;
+use juniper::{graphql_object, GraphQLEnum, GraphQLInputObject};
+use serde::{Deserialize, Serialize};
+use tauri_graphql_demo_macros::ConnectionNode;
+
+/// 可以撤销的写操作，每种对应 `Mutation` 上的一个同名字段
+#[derive(Debug, Clone, Copy, PartialEq, Eq, GraphQLEnum, sqlx::Type)]
+#[sqlx(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum OperationKind {
+    AddTodo,
+    EditTodo,
+    CompleteTodo,
+    RemoveTodo,
+    ToggleAll,
+    ClearCompleted,
+}
+
+/// 操作日志中的一条记录，撤销和重做都以整条记录为单位
+#[derive(Debug, Clone, sqlx::FromRow, ConnectionNode)]
+#[connection(cursor = "id")]
+pub struct Operation {
+    pub(crate) id: scalar::ID,
+    pub(crate) kind: OperationKind,
+    /// JSON 编码的 `Vec<TodoChange>`
+    pub(crate) changes: String,
+    pub(crate) undone: bool,
+    pub(crate) created_at: scalar::Timestamp,
+}
+
+impl Operation {
+    pub const TYPE_NAME: &'static str = "Operation";
+
+    pub fn changes(&self) -> Result<Vec<TodoChange>> {
+        Ok(serde_json::from_str(&self.changes)?)
+    }
+}
+
+#[graphql_object(
+    context = graphql::Context,
+    scalar = graphql::CustomScalarValue,
+    impl = relay::NodeValue
+)]
+impl Operation {
+    pub fn id(&self) -> relay::GlobalId {
+        relay::GlobalId::new(Self::TYPE_NAME, self.id)
+    }
+    pub fn kind(&self) -> OperationKind {
+        self.kind
+    }
+    /// 已被撤销，可以通过 `redo` 重做
+    pub fn undone(&self) -> bool {
+        self.undone
+    }
+    pub fn created_at(&self) -> &scalar::Timestamp {
+        &self.created_at
+    }
+    /// 受影响的事项，包括重复事项完成时创建的下一次事项
+    pub fn todo_ids(&self) -> Result<Vec<relay::GlobalId>> {
+        Ok(self
+            .changes()?
+            .into_iter()
+            .map(|it| relay::GlobalId::new(Todo::TYPE_NAME, it.id.into()))
+            .collect())
+    }
+}
+
+/// 事项中可以撤销的字段在某一时刻的状态，`trashed` 表示位于回收站
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TodoSnapshot {
+    pub(crate) description: String,
+    pub(crate) done: bool,
+    pub(crate) recurrence: Option<String>,
+    pub(crate) trashed: bool,
+}
+
+impl From<&Todo> for TodoSnapshot {
+    fn from(todo: &Todo) -> Self {
+        Self {
+            description: todo.description.clone(),
+            done: todo.done,
+            recurrence: todo.recurrence.clone(),
+            trashed: todo.deleted_at.is_some(),
+        }
+    }
+}
+
+/// 一个事项在操作前后的状态，撤销时写回 `before`，重做时写回 `after`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TodoChange {
+    pub(crate) id: i64,
+    pub(crate) before: TodoSnapshot,
+    pub(crate) after: TodoSnapshot,
+}
+
+impl TodoChange {
+    pub fn new(before: TodoSnapshot, after: &Todo) -> Self {
+        Self {
+            id: after.id.into(),
+            before,
+            after: after.into(),
+        }
+    }
+
+    /// 新建的事项，撤销时移入回收站
+    pub fn created(todo: &Todo) -> Self {
+        let before = TodoSnapshot {
+            trashed: true,
+            ..todo.into()
+        };
+        Self::new(before, todo)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, GraphQLEnum)]
+pub enum OperationOrderField {
+    CreatedAt,
+}
+
+impl OperationOrderField {
+    pub const fn column(self) -> &'static str {
+        match self {
+            // 操作 ID 按写入顺序递增，不会像只精确到秒的创建时间那样出现并列
+            Self::CreatedAt => "id",
+        }
+    }
+}
+
+/// 操作日志连接的排序键
+#[derive(Debug, Clone, GraphQLInputObject)]
+#[graphql(scalar = graphql::CustomScalarValue)]
+pub struct OperationOrder {
+    pub(crate) field: OperationOrderField,
+    #[graphql(default)]
+    pub(crate) direction: relay::OrderDirection,
+}
+
+impl relay::ConnectionOrder for OperationOrder {
+    const KEYS: &'static [&'static str] = &[OperationOrderField::CreatedAt.column()];
+
+    fn signature(&self) -> String {
+        format!("{}:{}", self.field.column(), self.direction.as_sql())
+    }
+
+    fn keys(&self) -> Vec<&'static str> {
+        vec![self.field.column()]
+    }
+}
@@ -0,0 +1,308 @@
+use crate::graphql::{self, relay, scalar};
+use crate::models::{
+    list::TodoList,
+    operation::Operation,
+    tag::Tag,
+    todo::{Todo, TodoFilter, TodoOrder},
+};
+use juniper::{graphql_object, GraphQLInputObject, GraphQLObject};
+
+#[derive(Debug, GraphQLInputObject)]
+#[graphql(scalar = graphql::CustomScalarValue)]
+pub struct AddTodoInput {
+    pub(crate) description: String,
+    /// 所属清单，默认为 Inbox
+    pub(crate) list_id: Option<relay::GlobalId>,
+    /// 客户端生成的标识，原样返回在结果中，用于匹配请求与响应
+    pub(crate) client_mutation_id: Option<String>,
+}
+
+#[derive(Debug, GraphQLInputObject)]
+#[graphql(scalar = graphql::CustomScalarValue)]
+pub struct CompleteTodoInput {
+    pub(crate) id: relay::GlobalId,
+    pub(crate) done: bool,
+    /// 同时修改所有子孙事项的完成状态
+    #[graphql(default)]
+    pub(crate) include_descendants: bool,
+    pub(crate) client_mutation_id: Option<String>,
+}
+
+#[derive(Debug, GraphQLInputObject)]
+#[graphql(scalar = graphql::CustomScalarValue)]
+pub struct EditTodoInput {
+    pub(crate) id: relay::GlobalId,
+    pub(crate) description: String,
+    pub(crate) client_mutation_id: Option<String>,
+}
+
+/// 截止时间和提醒时间会一起被覆盖，未传的字段视为清除
+#[derive(Debug, GraphQLInputObject)]
+#[graphql(scalar = graphql::CustomScalarValue)]
+pub struct ScheduleTodoInput {
+    pub(crate) id: relay::GlobalId,
+    pub(crate) due_at: Option<scalar::Timestamp>,
+    /// 修改后会重新提醒，早于当前时间时立即提醒
+    pub(crate) remind_at: Option<scalar::Timestamp>,
+    pub(crate) client_mutation_id: Option<String>,
+}
+
+#[derive(Debug, GraphQLInputObject)]
+#[graphql(scalar = graphql::CustomScalarValue)]
+pub struct SetRecurrenceInput {
+    pub(crate) id: relay::GlobalId,
+    /// iCalendar RRULE，例如 `FREQ=WEEKLY;BYDAY=MO,TH`，可以带 `RRULE:` 前缀
+    pub(crate) rule: String,
+    pub(crate) client_mutation_id: Option<String>,
+}
+
+#[derive(Debug, GraphQLInputObject)]
+#[graphql(scalar = graphql::CustomScalarValue)]
+pub struct ClearRecurrenceInput {
+    pub(crate) id: relay::GlobalId,
+    pub(crate) client_mutation_id: Option<String>,
+}
+
+/// `beforeId` 与 `afterId` 必须且只能传一个
+#[derive(Debug, GraphQLInputObject)]
+#[graphql(scalar = graphql::CustomScalarValue)]
+pub struct MoveTodoInput {
+    pub(crate) id: relay::GlobalId,
+    /// 移动到该事项之前
+    pub(crate) before_id: Option<relay::GlobalId>,
+    /// 移动到该事项之后
+    pub(crate) after_id: Option<relay::GlobalId>,
+    pub(crate) client_mutation_id: Option<String>,
+}
+
+/// 子事项会随之移动，并与新的父事项位于同一清单
+#[derive(Debug, GraphQLInputObject)]
+#[graphql(scalar = graphql::CustomScalarValue)]
+pub struct MoveTodoUnderInput {
+    pub(crate) id: relay::GlobalId,
+    /// 新的父事项，为 null 时移到顶层
+    pub(crate) parent_id: Option<relay::GlobalId>,
+    pub(crate) client_mutation_id: Option<String>,
+}
+
+#[derive(Debug, GraphQLInputObject)]
+#[graphql(scalar = graphql::CustomScalarValue)]
+pub struct RemoveTodoInput {
+    pub(crate) id: relay::GlobalId,
+    pub(crate) client_mutation_id: Option<String>,
+}
+
+#[derive(Debug, GraphQLInputObject)]
+#[graphql(scalar = graphql::CustomScalarValue)]
+pub struct ToggleAllInput {
+    pub(crate) done: bool,
+    /// 只修改该清单中的事项，默认为 Inbox
+    pub(crate) list_id: Option<relay::GlobalId>,
+    pub(crate) client_mutation_id: Option<String>,
+}
+
+#[derive(Debug, Default, GraphQLInputObject)]
+#[graphql(scalar = graphql::CustomScalarValue)]
+pub struct ClearCompletedInput {
+    /// 只清除该清单中的事项，默认为 Inbox
+    pub(crate) list_id: Option<relay::GlobalId>,
+    pub(crate) client_mutation_id: Option<String>,
+}
+
+#[derive(Debug, GraphQLInputObject)]
+#[graphql(scalar = graphql::CustomScalarValue)]
+pub struct RestoreTodoInput {
+    pub(crate) id: relay::GlobalId,
+    pub(crate) client_mutation_id: Option<String>,
+}
+
+#[derive(Debug, Default, GraphQLInputObject)]
+#[graphql(scalar = graphql::CustomScalarValue)]
+pub struct RestoreAllInput {
+    /// 只恢复该清单中的事项，默认恢复所有清单
+    pub(crate) list_id: Option<relay::GlobalId>,
+    pub(crate) client_mutation_id: Option<String>,
+}
+
+#[derive(Debug, Default, GraphQLInputObject)]
+#[graphql(scalar = graphql::CustomScalarValue)]
+pub struct EmptyTrashInput {
+    pub(crate) client_mutation_id: Option<String>,
+}
+
+#[derive(Debug, Default, GraphQLInputObject)]
+#[graphql(scalar = graphql::CustomScalarValue)]
+pub struct UndoInput {
+    pub(crate) client_mutation_id: Option<String>,
+}
+
+#[derive(Debug, Default, GraphQLInputObject)]
+#[graphql(scalar = graphql::CustomScalarValue)]
+pub struct RedoInput {
+    pub(crate) client_mutation_id: Option<String>,
+}
+
+#[derive(Debug, GraphQLInputObject)]
+#[graphql(scalar = graphql::CustomScalarValue)]
+pub struct CreateListInput {
+    pub(crate) name: String,
+    /// 格式为 `#RRGGBB`
+    pub(crate) color: Option<String>,
+    pub(crate) client_mutation_id: Option<String>,
+}
+
+/// 未传的字段保持不变
+#[derive(Debug, GraphQLInputObject)]
+#[graphql(scalar = graphql::CustomScalarValue)]
+pub struct UpdateListInput {
+    pub(crate) id: relay::GlobalId,
+    pub(crate) name: Option<String>,
+    pub(crate) color: Option<String>,
+    pub(crate) archived: Option<bool>,
+    pub(crate) client_mutation_id: Option<String>,
+}
+
+#[derive(Debug, GraphQLInputObject)]
+#[graphql(scalar = graphql::CustomScalarValue)]
+pub struct CreateTagInput {
+    /// 首尾空白会被去掉，不能与已有标签重名（不区分大小写）
+    pub(crate) name: String,
+    pub(crate) client_mutation_id: Option<String>,
+}
+
+#[derive(Debug, GraphQLInputObject)]
+#[graphql(scalar = graphql::CustomScalarValue)]
+pub struct RenameTagInput {
+    pub(crate) id: relay::GlobalId,
+    pub(crate) name: String,
+    pub(crate) client_mutation_id: Option<String>,
+}
+
+#[derive(Debug, GraphQLInputObject)]
+#[graphql(scalar = graphql::CustomScalarValue)]
+pub struct DeleteTagInput {
+    pub(crate) id: relay::GlobalId,
+    pub(crate) client_mutation_id: Option<String>,
+}
+
+#[derive(Debug, GraphQLInputObject)]
+#[graphql(scalar = graphql::CustomScalarValue)]
+pub struct TagTodoInput {
+    pub(crate) todo_id: relay::GlobalId,
+    pub(crate) tag_id: relay::GlobalId,
+    pub(crate) client_mutation_id: Option<String>,
+}
+
+#[derive(Debug, GraphQLInputObject)]
+#[graphql(scalar = graphql::CustomScalarValue)]
+pub struct UntagTodoInput {
+    pub(crate) todo_id: relay::GlobalId,
+    pub(crate) tag_id: relay::GlobalId,
+    pub(crate) client_mutation_id: Option<String>,
+}
+
+pub struct AddTodoPayload {
+    pub(crate) todo: Todo,
+    pub(crate) client_mutation_id: Option<String>,
+}
+
+#[graphql_object(context = graphql::Context, scalar = graphql::CustomScalarValue)]
+impl AddTodoPayload {
+    /// 新增的事项
+    pub fn todo(&self) -> &Todo {
+        &self.todo
+    }
+    /// 新增事项对应的边，`filter` 和 `orderBy` 需与客户端插入的连接保持一致，游标才能用于继续分页
+    pub fn todo_edge(
+        &self,
+        filter: Option<TodoFilter>,
+        order_by: Option<Vec<TodoOrder>>,
+    ) -> relay::ConnectionEdge<Todo> {
+        relay::ConnectionEdge::new(
+            self.todo.clone(),
+            &filter.unwrap_or_default(),
+            &order_by.unwrap_or_default(),
+        )
+    }
+    pub fn client_mutation_id(&self) -> Option<&str> {
+        self.client_mutation_id.as_deref()
+    }
+}
+
+#[derive(GraphQLObject)]
+#[graphql(context = graphql::Context, scalar = graphql::CustomScalarValue)]
+pub struct UpdateTodoPayload {
+    /// 修改后的事项
+    pub(crate) todo: Todo,
+    pub(crate) client_mutation_id: Option<String>,
+}
+
+#[derive(GraphQLObject)]
+#[graphql(context = graphql::Context, scalar = graphql::CustomScalarValue)]
+pub struct RemoveTodoPayload {
+    pub(crate) deleted_todo_id: relay::GlobalId,
+    pub(crate) client_mutation_id: Option<String>,
+}
+
+#[derive(GraphQLObject)]
+#[graphql(context = graphql::Context, scalar = graphql::CustomScalarValue)]
+pub struct ToggleAllPayload {
+    /// 状态实际发生变化的事项，原本就处于目标状态的事项不包含在内
+    pub(crate) updated_todos: Vec<Todo>,
+    pub(crate) client_mutation_id: Option<String>,
+}
+
+#[derive(GraphQLObject)]
+#[graphql(context = graphql::Context, scalar = graphql::CustomScalarValue)]
+pub struct ClearCompletedPayload {
+    pub(crate) deleted_todo_ids: Vec<relay::GlobalId>,
+    pub(crate) client_mutation_id: Option<String>,
+}
+
+#[derive(GraphQLObject)]
+#[graphql(context = graphql::Context, scalar = graphql::CustomScalarValue)]
+pub struct RestoreAllPayload {
+    pub(crate) restored_todos: Vec<Todo>,
+    pub(crate) client_mutation_id: Option<String>,
+}
+
+#[derive(GraphQLObject)]
+#[graphql(context = graphql::Context, scalar = graphql::CustomScalarValue)]
+pub struct EmptyTrashPayload {
+    /// 被彻底删除的事项，无法再恢复
+    pub(crate) deleted_todo_ids: Vec<relay::GlobalId>,
+    pub(crate) client_mutation_id: Option<String>,
+}
+
+#[derive(GraphQLObject)]
+#[graphql(context = graphql::Context, scalar = graphql::CustomScalarValue)]
+pub struct HistoryPayload {
+    /// 被撤销或重做的操作，没有可撤销或重做的操作时为空
+    pub(crate) operation: Option<Operation>,
+    /// 状态被写回的事项，包括因此移入回收站的事项
+    pub(crate) todos: Vec<Todo>,
+    pub(crate) client_mutation_id: Option<String>,
+}
+
+#[derive(GraphQLObject)]
+#[graphql(context = graphql::Context, scalar = graphql::CustomScalarValue)]
+pub struct TagPayload {
+    /// 新建或修改后的标签
+    pub(crate) tag: Tag,
+    pub(crate) client_mutation_id: Option<String>,
+}
+
+#[derive(GraphQLObject)]
+#[graphql(context = graphql::Context, scalar = graphql::CustomScalarValue)]
+pub struct DeleteTagPayload {
+    pub(crate) deleted_tag_id: relay::GlobalId,
+    pub(crate) client_mutation_id: Option<String>,
+}
+
+#[derive(GraphQLObject)]
+#[graphql(context = graphql::Context, scalar = graphql::CustomScalarValue)]
+pub struct ListPayload {
+    /// 新建或修改后的清单
+    pub(crate) list: TodoList,
+    pub(crate) client_mutation_id: Option<String>,
+}
@@ -0,0 +1,489 @@
+use std::fmt;
+use std::str::FromStr;
+
+use chrono::{
+    DateTime, Datelike, Days, Local, Months, NaiveDate, NaiveDateTime, TimeDelta, TimeZone, Weekday,
+};
+
+use crate::error::Error;
+use crate::graphql::scalar;
+
+/// 计算重复时最多检查的周期数，避免 `FREQ=WEEKLY;BYMONTHDAY=31` 这类很少命中的规则长时间循环
+const MAX_PERIODS: u32 = 10_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Frequency {
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+}
+
+impl Frequency {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Daily => "DAILY",
+            Self::Weekly => "WEEKLY",
+            Self::Monthly => "MONTHLY",
+            Self::Yearly => "YEARLY",
+        }
+    }
+}
+
+/// iCalendar（RFC 5545）RRULE 的子集，以事项的截止时间作为 DTSTART
+///
+/// 支持 `FREQ`、`INTERVAL`、`BYDAY`（不带序号）、`BYMONTHDAY`、`COUNT` 和 `UNTIL`。
+/// 按本地时间计算，夏令时切换前后保持相同的钟点
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Recurrence {
+    freq: Frequency,
+    interval: u32,
+    by_day: Vec<Weekday>,
+    /// 1 到 31 表示当月第几天，-1 到 -31 表示倒数第几天
+    by_month_day: Vec<i32>,
+    /// 包括当前这一次在内剩余的次数
+    count: Option<u32>,
+    until: Option<scalar::Timestamp>,
+}
+
+impl Recurrence {
+    /// 截止时间为 `due_at` 的这一次之后的下一次，以及下一次事项使用的规则（`COUNT` 减一）
+    pub fn next_after(&self, due_at: scalar::Timestamp) -> Option<(scalar::Timestamp, Self)> {
+        let next = self.occurrences_after(due_at, 1).into_iter().next()?;
+        let rule = Self {
+            count: self.count.map(|count| count - 1),
+            ..self.clone()
+        };
+        Some((next, rule))
+    }
+
+    /// 截止时间为 `due_at` 的这一次之后至多 `limit` 次重复的时间
+    pub fn occurrences_after(
+        &self,
+        due_at: scalar::Timestamp,
+        limit: usize,
+    ) -> Vec<scalar::Timestamp> {
+        let Some(start) = DateTime::from_timestamp(due_at.into(), 0) else {
+            return Vec::new();
+        };
+        let start = start.with_timezone(&Local).naive_local();
+        // COUNT 包括 DTSTART 这一次
+        let limit = match self.count {
+            Some(count) => limit.min(count.saturating_sub(1) as usize),
+            None => limit,
+        };
+
+        let mut occurrences = Vec::with_capacity(limit);
+        for period in 0..MAX_PERIODS {
+            if occurrences.len() >= limit {
+                break;
+            }
+            for candidate in self.candidates(start, period).unwrap_or_default() {
+                if candidate <= start {
+                    continue;
+                }
+                let timestamp = to_timestamp(candidate);
+                if self
+                    .until
+                    .is_some_and(|until| i64::from(timestamp) > i64::from(until))
+                {
+                    return occurrences;
+                }
+                occurrences.push(timestamp);
+                if occurrences.len() >= limit {
+                    break;
+                }
+            }
+        }
+        occurrences
+    }
+
+    /// 第 `period` 个周期内按时间顺序排列的候选时间，超出日期范围时返回 `None`
+    fn candidates(&self, start: NaiveDateTime, period: u32) -> Option<Vec<NaiveDateTime>> {
+        let step = period.checked_mul(self.interval)?;
+        let date = start.date();
+        let dates = match self.freq {
+            Frequency::Daily => vec![date.checked_add_days(Days::new(step.into()))?],
+            Frequency::Weekly => {
+                let monday = date
+                    .checked_add_days(Days::new(u64::from(step) * 7))?
+                    .checked_sub_days(Days::new(date.weekday().num_days_from_monday().into()))?;
+                let weekdays = if self.by_day.is_empty() {
+                    vec![date.weekday()]
+                } else {
+                    self.by_day.clone()
+                };
+                weekdays
+                    .into_iter()
+                    .map(|it| monday + Days::new(it.num_days_from_monday().into()))
+                    .collect()
+            }
+            Frequency::Monthly | Frequency::Yearly => {
+                let months = match self.freq {
+                    Frequency::Yearly => step.checked_mul(12)?,
+                    _ => step,
+                };
+                let first = date.with_day(1)?.checked_add_months(Months::new(months))?;
+                first
+                    .iter_days()
+                    .take_while(|it| it.month() == first.month())
+                    .collect()
+            }
+        };
+        Some(
+            dates
+                .into_iter()
+                .filter(|it| self.matches(*it, date))
+                .map(|it| it.and_time(start.time()))
+                .collect(),
+        )
+    }
+
+    fn matches(&self, date: NaiveDate, start: NaiveDate) -> bool {
+        if !self.by_day.is_empty() && !self.by_day.contains(&date.weekday()) {
+            return false;
+        }
+        if !self.by_month_day.is_empty() {
+            let day = date.day() as i32;
+            let last = last_day_of_month(date) as i32;
+            return self
+                .by_month_day
+                .iter()
+                .any(|it| *it == day || *it == day - last - 1);
+        }
+        match self.freq {
+            Frequency::Monthly | Frequency::Yearly if self.by_day.is_empty() => {
+                date.day() == start.day()
+            }
+            _ => true,
+        }
+    }
+}
+
+impl FromStr for Recurrence {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let rule = s.trim();
+        let rule = rule
+            .strip_prefix("RRULE:")
+            .or_else(|| rule.strip_prefix("rrule:"))
+            .unwrap_or(rule);
+
+        let mut freq = None;
+        let mut interval = 1;
+        let mut by_day = Vec::new();
+        let mut by_month_day = Vec::new();
+        let mut count = None;
+        let mut until = None;
+        for part in rule.split(';').filter(|it| !it.is_empty()) {
+            let (name, value) = part
+                .split_once('=')
+                .ok_or_else(|| invalid(format!("malformed part '{part}'")))?;
+            let value = value.to_ascii_uppercase();
+            match name.to_ascii_uppercase().as_str() {
+                "FREQ" => {
+                    freq = Some(match value.as_str() {
+                        "DAILY" => Frequency::Daily,
+                        "WEEKLY" => Frequency::Weekly,
+                        "MONTHLY" => Frequency::Monthly,
+                        "YEARLY" => Frequency::Yearly,
+                        _ => return Err(invalid(format!("unsupported FREQ '{value}'"))),
+                    })
+                }
+                "INTERVAL" => interval = parse_positive("INTERVAL", &value)?,
+                "BYDAY" => {
+                    by_day = value
+                        .split(',')
+                        .map(parse_weekday)
+                        .collect::<Result<_, _>>()?
+                }
+                "BYMONTHDAY" => {
+                    by_month_day = value
+                        .split(',')
+                        .map(parse_month_day)
+                        .collect::<Result<_, _>>()?
+                }
+                "COUNT" => count = Some(parse_positive("COUNT", &value)?),
+                "UNTIL" => until = Some(parse_until(&value)?),
+                _ => return Err(invalid(format!("unsupported part '{name}'"))),
+            }
+        }
+
+        let freq = freq.ok_or_else(|| invalid("FREQ is required"))?;
+        if count.is_some() && until.is_some() {
+            return Err(invalid("COUNT and UNTIL cannot be used together"));
+        }
+        if freq == Frequency::Yearly && !(by_day.is_empty() && by_month_day.is_empty()) {
+            return Err(invalid(
+                "BYDAY and BYMONTHDAY are not supported with FREQ=YEARLY",
+            ));
+        }
+        by_day.sort_by_key(|it: &Weekday| it.num_days_from_monday());
+        by_day.dedup();
+        by_month_day.sort_unstable();
+        by_month_day.dedup();
+        Ok(Self {
+            freq,
+            interval,
+            by_day,
+            by_month_day,
+            count,
+            until,
+        })
+    }
+}
+
+/// 规范化的 RRULE 文本，保存到数据库以及对外展示时使用
+impl fmt::Display for Recurrence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "FREQ={}", self.freq.as_str())?;
+        if self.interval != 1 {
+            write!(f, ";INTERVAL={}", self.interval)?;
+        }
+        if !self.by_day.is_empty() {
+            let days = self
+                .by_day
+                .iter()
+                .map(|it| weekday_code(*it))
+                .collect::<Vec<_>>();
+            write!(f, ";BYDAY={}", days.join(","))?;
+        }
+        if !self.by_month_day.is_empty() {
+            let days = self
+                .by_month_day
+                .iter()
+                .map(|it| it.to_string())
+                .collect::<Vec<_>>();
+            write!(f, ";BYMONTHDAY={}", days.join(","))?;
+        }
+        if let Some(count) = self.count {
+            write!(f, ";COUNT={count}")?;
+        }
+        if let Some(until) = self
+            .until
+            .and_then(|it| DateTime::from_timestamp(it.into(), 0))
+        {
+            write!(f, ";UNTIL={}", until.format("%Y%m%dT%H%M%SZ"))?;
+        }
+        Ok(())
+    }
+}
+
+fn invalid(message: impl fmt::Display) -> Error {
+    Error::Validation(format!("Invalid recurrence rule: {message}"))
+}
+
+fn parse_positive(name: &str, value: &str) -> Result<u32, Error> {
+    value
+        .parse::<u32>()
+        .ok()
+        .filter(|it| *it > 0)
+        .ok_or_else(|| invalid(format!("{name} must be a positive integer")))
+}
+
+fn parse_weekday(value: &str) -> Result<Weekday, Error> {
+    match value {
+        "MO" => Ok(Weekday::Mon),
+        "TU" => Ok(Weekday::Tue),
+        "WE" => Ok(Weekday::Wed),
+        "TH" => Ok(Weekday::Thu),
+        "FR" => Ok(Weekday::Fri),
+        "SA" => Ok(Weekday::Sat),
+        "SU" => Ok(Weekday::Sun),
+        _ => Err(invalid(format!("unsupported BYDAY value '{value}'"))),
+    }
+}
+
+fn weekday_code(weekday: Weekday) -> &'static str {
+    match weekday {
+        Weekday::Mon => "MO",
+        Weekday::Tue => "TU",
+        Weekday::Wed => "WE",
+        Weekday::Thu => "TH",
+        Weekday::Fri => "FR",
+        Weekday::Sat => "SA",
+        Weekday::Sun => "SU",
+    }
+}
+
+fn parse_month_day(value: &str) -> Result<i32, Error> {
+    value
+        .parse::<i32>()
+        .ok()
+        .filter(|it| (1..=31).contains(&it.abs()))
+        .ok_or_else(|| invalid(format!("invalid BYMONTHDAY value '{value}'")))
+}
+
+/// 支持 `YYYYMMDD`（当天结束前都有效）、UTC 的 `YYYYMMDDTHHMMSSZ` 和本地时间的 `YYYYMMDDTHHMMSS`
+fn parse_until(value: &str) -> Result<scalar::Timestamp, Error> {
+    let error = || invalid(format!("invalid UNTIL value '{value}'"));
+    if let Some(utc) = value.strip_suffix('Z') {
+        let until = NaiveDateTime::parse_from_str(utc, "%Y%m%dT%H%M%S").map_err(|_| error())?;
+        return Ok(until.and_utc().timestamp().into());
+    }
+    let until = match NaiveDate::parse_from_str(value, "%Y%m%d") {
+        Ok(date) => date.and_hms_opt(23, 59, 59).ok_or_else(error)?,
+        Err(_) => NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S").map_err(|_| error())?,
+    };
+    Ok(to_timestamp(until))
+}
+
+fn last_day_of_month(date: NaiveDate) -> u32 {
+    (28..=31)
+        .rev()
+        .find(|day| date.with_day(*day).is_some())
+        .unwrap_or(28)
+}
+
+/// 本地时间落在夏令时跳过的时间段时顺延一小时
+fn to_timestamp(local: NaiveDateTime) -> scalar::Timestamp {
+    Local
+        .from_local_datetime(&local)
+        .earliest()
+        .or_else(|| {
+            Local
+                .from_local_datetime(&(local + TimeDelta::hours(1)))
+                .earliest()
+        })
+        .map_or_else(|| local.and_utc().timestamp(), |it| it.timestamp())
+        .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 本地时间，结果与运行测试的时区无关
+    fn at(date: &str) -> scalar::Timestamp {
+        to_timestamp(NaiveDateTime::parse_from_str(date, "%Y-%m-%d %H:%M").unwrap())
+    }
+
+    fn local(timestamps: Vec<scalar::Timestamp>) -> Vec<String> {
+        timestamps
+            .into_iter()
+            .map(|it| {
+                DateTime::from_timestamp(it.into(), 0)
+                    .unwrap()
+                    .with_timezone(&Local)
+                    .format("%Y-%m-%d %H:%M")
+                    .to_string()
+            })
+            .collect()
+    }
+
+    fn occurrences(rule: &str, start: &str, limit: usize) -> Vec<String> {
+        let rule = rule.parse::<Recurrence>().unwrap();
+        local(rule.occurrences_after(at(start), limit))
+    }
+
+    #[test]
+    fn normalizes_rules() {
+        let rule = "rrule:freq=weekly;byday=fr,mo,mo;interval=2"
+            .parse::<Recurrence>()
+            .unwrap();
+        assert_eq!(rule.to_string(), "FREQ=WEEKLY;INTERVAL=2;BYDAY=MO,FR");
+
+        for text in [
+            "FREQ=DAILY",
+            "FREQ=WEEKLY;INTERVAL=2;BYDAY=MO,FR;COUNT=5",
+            "FREQ=MONTHLY;BYMONTHDAY=-1,1,15",
+            "FREQ=DAILY;UNTIL=20261231T120000Z",
+        ] {
+            let rule = text.parse::<Recurrence>().unwrap();
+            assert_eq!(rule.to_string(), text);
+            assert_eq!(rule.to_string().parse::<Recurrence>().unwrap(), rule);
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_rules() {
+        for text in [
+            "",
+            "INTERVAL=2",
+            "FREQ",
+            "FREQ=HOURLY",
+            "FREQ=DAILY;INTERVAL=0",
+            "FREQ=DAILY;COUNT=-1",
+            "FREQ=WEEKLY;BYDAY=1MO",
+            "FREQ=MONTHLY;BYMONTHDAY=0",
+            "FREQ=MONTHLY;BYMONTHDAY=32",
+            "FREQ=DAILY;UNTIL=tomorrow",
+            "FREQ=DAILY;COUNT=2;UNTIL=20261231",
+            "FREQ=YEARLY;BYDAY=MO",
+            "FREQ=DAILY;BYSETPOS=1",
+        ] {
+            assert!(
+                matches!(text.parse::<Recurrence>(), Err(Error::Validation(_))),
+                "{text}"
+            );
+        }
+    }
+
+    #[test]
+    fn weekly_by_day() {
+        // 2026-10-14 是星期三
+        assert_eq!(
+            occurrences("FREQ=WEEKLY;BYDAY=MO,WE,FR", "2026-10-14 09:00", 4),
+            [
+                "2026-10-16 09:00",
+                "2026-10-19 09:00",
+                "2026-10-21 09:00",
+                "2026-10-23 09:00"
+            ]
+        );
+        assert_eq!(
+            occurrences("FREQ=WEEKLY;INTERVAL=2;BYDAY=MO", "2026-10-14 09:00", 2),
+            ["2026-10-26 09:00", "2026-11-09 09:00"]
+        );
+    }
+
+    #[test]
+    fn monthly_by_month_day() {
+        assert_eq!(
+            occurrences("FREQ=MONTHLY;BYMONTHDAY=-1", "2026-01-31 09:00", 3),
+            ["2026-02-28 09:00", "2026-03-31 09:00", "2026-04-30 09:00"]
+        );
+        // 没有 BYMONTHDAY 时跳过没有这一天的月份
+        assert_eq!(
+            occurrences("FREQ=MONTHLY", "2026-01-31 09:00", 2),
+            ["2026-03-31 09:00", "2026-05-31 09:00"]
+        );
+    }
+
+    #[test]
+    fn stops_at_until_and_count() {
+        assert_eq!(
+            occurrences("FREQ=DAILY;UNTIL=20261016", "2026-10-14 09:00", 10),
+            ["2026-10-15 09:00", "2026-10-16 09:00"]
+        );
+        assert_eq!(
+            occurrences("FREQ=DAILY;COUNT=3", "2026-10-14 09:00", 10),
+            ["2026-10-15 09:00", "2026-10-16 09:00"]
+        );
+    }
+
+    #[test]
+    fn next_after_decrements_count() {
+        let rule = "FREQ=DAILY;COUNT=3".parse::<Recurrence>().unwrap();
+        let (due_at, rule) = rule.next_after(at("2026-10-14 09:00")).unwrap();
+        assert_eq!(local(vec![due_at]), ["2026-10-15 09:00"]);
+        assert_eq!(rule.to_string(), "FREQ=DAILY;COUNT=2");
+
+        let (due_at, rule) = rule.next_after(due_at).unwrap();
+        assert_eq!(local(vec![due_at]), ["2026-10-16 09:00"]);
+        assert_eq!(rule.to_string(), "FREQ=DAILY;COUNT=1");
+        assert!(rule.next_after(due_at).is_none());
+    }
+
+    #[test]
+    fn gives_up_after_max_periods() {
+        // 二月没有 30 日，每 12 个月检查一次永远不会命中
+        assert!(occurrences(
+            "FREQ=MONTHLY;INTERVAL=12;BYMONTHDAY=30",
+            "2026-02-01 09:00",
+            1
+        )
+        .is_empty());
+    }
+}
@@ -0,0 +1,113 @@
+use crate::graphql::relay::{self, ConnectionOrder, CursorValue, EdgeData};
+use juniper::{meta::Field, ExecutionResult, Executor, Registry, ScalarValue};
+
+use super::todo::Todo;
+
+/// 全文搜索命中的附加信息，作为边上的字段暴露
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct SearchMatch {
+    pub(crate) rank: f64,
+    pub(crate) snippet: String,
+}
+
+impl SearchMatch {
+    /// `snippet()` 标记命中部分的起止字符；控制字符无法通过输入框录入，不会与描述原文混淆
+    pub const MARK_START: char = '\u{2}';
+    pub const MARK_END: char = '\u{3}';
+
+    /// 转义 `snippet()` 输出中的 HTML 特殊字符，再把标记字符替换为 `<mark></mark>`
+    pub fn highlight(raw: &str) -> String {
+        let mut html = String::with_capacity(raw.len() + 16);
+        for c in raw.chars() {
+            match c {
+                Self::MARK_START => html.push_str("<mark>"),
+                Self::MARK_END => html.push_str("</mark>"),
+                '&' => html.push_str("&amp;"),
+                '<' => html.push_str("&lt;"),
+                '>' => html.push_str("&gt;"),
+                '"' => html.push_str("&quot;"),
+                '\'' => html.push_str("&#39;"),
+                c => html.push(c),
+            }
+        }
+        html
+    }
+}
+
+/// 搜索结果固定按相关度排序，与其他排序一样隐式追加 `id ASC` 作为决胜键
+///
+/// bm25 相关度依赖整个索引的统计，索引变化后同一事项的相关度也会变化，
+/// 因此游标只在索引不变时保证翻页不重不漏
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SearchOrder;
+
+impl ConnectionOrder for SearchOrder {
+    const KEYS: &'static [&'static str] = &["rank"];
+
+    fn signature(&self) -> String {
+        String::from("rank:ASC")
+    }
+
+    fn keys(&self) -> Vec<&'static str> {
+        vec!["rank"]
+    }
+}
+
+impl EdgeData<Todo> for SearchMatch {
+    type Order = SearchOrder;
+    type ConnectionFields = ();
+    const CONNECTION_TYPE_NAME: &'static str = "TodoSearchConnection";
+    const EDGE_TYPE_NAME: &'static str = "TodoSearchEdge";
+
+    fn cursor(&self, node: &Todo, order: &Self::Order) -> relay::Cursor {
+        relay::Cursor::new(
+            Self::CONNECTION_TYPE_NAME,
+            order.signature(),
+            vec![CursorValue::Float(self.rank)],
+            node.id,
+        )
+    }
+
+    fn fields<'r, S>(registry: &mut Registry<'r, S>) -> Vec<Field<'r, S>>
+    where
+        S: ScalarValue + 'r,
+    {
+        vec![
+            registry
+                .field::<&f64>("rank", &())
+                .description("bm25 相关度，数值越小越相关"),
+            registry
+                .field::<&String>("snippet", &())
+                .description("命中片段的 HTML，原文已转义，匹配部分以 <mark></mark> 包裹"),
+        ]
+    }
+
+    fn resolve_field<C, S>(
+        &self,
+        field_name: &str,
+        executor: &Executor<C, S>,
+    ) -> Option<ExecutionResult<S>>
+    where
+        S: ScalarValue,
+    {
+        match field_name {
+            "rank" => Some(executor.resolve_with_ctx(&(), &self.rank)),
+            "snippet" => Some(executor.resolve_with_ctx(&(), &self.snippet)),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn highlight_escapes_description() {
+        let raw = "\u{2}<img src=x onerror=\"alert(1)\">\u{3} & 'milk'";
+        assert_eq!(
+            SearchMatch::highlight(raw),
+            "<mark>&lt;img src=x onerror=&quot;alert(1)&quot;&gt;</mark> &amp; &#39;milk&#39;"
+        );
+    }
+}
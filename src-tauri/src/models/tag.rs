@@ -0,0 +1,83 @@
+use crate::graphql::{self, relay, scalar};
+use juniper::{graphql_object, GraphQLEnum, GraphQLInputObject};
+use serde::Serialize;
+use tauri_graphql_demo_macros::ConnectionNode;
+
+/// 标签，事项与标签为多对多关系
+#[derive(Debug, Clone, Serialize, sqlx::FromRow, ConnectionNode)]
+#[connection(cursor = "id, name, created_at, usage_count")]
+pub struct Tag {
+    pub(crate) id: scalar::ID,
+    pub(crate) name: String,
+    pub(crate) created_at: scalar::Timestamp,
+    /// 查询时统计，不是表中的列
+    pub(crate) usage_count: i64,
+}
+
+impl Tag {
+    pub const TYPE_NAME: &'static str = "Tag";
+}
+
+#[graphql_object(
+    context = graphql::Context,
+    scalar = graphql::CustomScalarValue,
+    impl = relay::NodeValue
+)]
+impl Tag {
+    pub fn id(&self) -> relay::GlobalId {
+        relay::GlobalId::new(Self::TYPE_NAME, self.id)
+    }
+    /// 标签名，不区分大小写地唯一
+    pub fn name(&self) -> &String {
+        &self.name
+    }
+    pub fn created_at(&self) -> &scalar::Timestamp {
+        &self.created_at
+    }
+    /// 带有该标签的事项数
+    pub fn usage_count(&self) -> i32 {
+        self.usage_count as i32
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, GraphQLEnum)]
+pub enum TagOrderField {
+    Name,
+    CreatedAt,
+    UsageCount,
+}
+
+impl TagOrderField {
+    pub const fn column(self) -> &'static str {
+        match self {
+            Self::Name => "name",
+            Self::CreatedAt => "created_at",
+            Self::UsageCount => "usage_count",
+        }
+    }
+}
+
+/// 标签连接的排序键，多个排序键按顺序依次比较
+#[derive(Debug, Clone, GraphQLInputObject)]
+#[graphql(scalar = graphql::CustomScalarValue)]
+pub struct TagOrder {
+    pub(crate) field: TagOrderField,
+    #[graphql(default)]
+    pub(crate) direction: relay::OrderDirection,
+}
+
+impl relay::ConnectionOrder for TagOrder {
+    const KEYS: &'static [&'static str] = &[
+        TagOrderField::Name.column(),
+        TagOrderField::CreatedAt.column(),
+        TagOrderField::UsageCount.column(),
+    ];
+
+    fn signature(&self) -> String {
+        format!("{}:{}", self.field.column(), self.direction.as_sql())
+    }
+
+    fn keys(&self) -> Vec<&'static str> {
+        vec![self.field.column()]
+    }
+}
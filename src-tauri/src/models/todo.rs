@@ -1,19 +1,53 @@
+use crate::error::Error;
 use crate::graphql::{self, relay, scalar};
-use juniper::graphql_object;
+use crate::models::{
+    list::TodoList,
+    recurrence::Recurrence,
+    tag::{Tag, TagOrder},
+};
+use juniper::{
+    graphql_object, graphql_value, Executor, FieldError, FieldResult, GraphQLEnum,
+    GraphQLInputObject,
+};
 use serde::Serialize;
+use tauri_graphql_demo_macros::{ConnectionFields, ConnectionNode};
 
-#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+#[derive(Debug, Clone, Serialize, sqlx::FromRow, ConnectionNode)]
+#[connection(
+    cursor = "id, created_at, description, done, updated_at, position",
+    fields = TodoCounts
+)]
 pub struct Todo {
-    id: scalar::ID,
-    description: String,
-    done: bool,
-    created_at: scalar::Timestamp,
+    pub(crate) id: scalar::ID,
+    pub(crate) description: String,
+    pub(crate) done: bool,
+    pub(crate) created_at: scalar::Timestamp,
+    pub(crate) updated_at: scalar::Timestamp,
+    /// 手动排序的位置，只用于排序，不对外暴露
+    pub(crate) position: i64,
+    pub(crate) due_at: Option<scalar::Timestamp>,
+    pub(crate) remind_at: Option<scalar::Timestamp>,
+    pub(crate) list_id: scalar::ID,
+    pub(crate) parent_id: Option<scalar::ID>,
+    /// 规范化的 RRULE 文本
+    pub(crate) recurrence: Option<String>,
+    pub(crate) deleted_at: Option<scalar::Timestamp>,
 }
 
-#[graphql_object(context = graphql::Context, scalar = graphql::CustomScalarValue)]
 impl Todo {
-    pub fn id(&self) -> &scalar::ID {
-        &self.id
+    pub const TYPE_NAME: &'static str = "Todo";
+    /// `nextOccurrences` 一次最多预览的次数
+    pub const MAX_OCCURRENCES: i32 = 100;
+}
+
+#[graphql_object(
+    context = graphql::Context,
+    scalar = graphql::CustomScalarValue,
+    impl = relay::NodeValue
+)]
+impl Todo {
+    pub fn id(&self) -> relay::GlobalId {
+        relay::GlobalId::new(Self::TYPE_NAME, self.id)
     }
     pub fn description(&self) -> &String {
         &self.description
@@ -24,12 +58,239 @@ impl Todo {
     pub fn created_at(&self) -> &scalar::Timestamp {
         &self.created_at
     }
+    pub fn updated_at(&self) -> &scalar::Timestamp {
+        &self.updated_at
+    }
+    /// 截止时间，未设置时为 null
+    pub fn due_at(&self) -> Option<&scalar::Timestamp> {
+        self.due_at.as_ref()
+    }
+    /// 提醒时间，到达时应用会收到 `todo-reminder` 事件
+    pub fn remind_at(&self) -> Option<&scalar::Timestamp> {
+        self.remind_at.as_ref()
+    }
+    /// 移入回收站的时间，不在回收站中时为 null
+    pub fn deleted_at(&self) -> Option<&scalar::Timestamp> {
+        self.deleted_at.as_ref()
+    }
+    /// 重复规则（iCalendar RRULE），不重复的事项为 null；完成后会创建下一次重复的事项
+    pub fn recurrence(&self) -> Option<&str> {
+        self.recurrence.as_deref()
+    }
+    /// 预览当前截止时间之后的重复时间，不重复的事项为空列表
+    pub fn next_occurrences(
+        &self,
+        #[graphql(default = 5)] count: i32,
+    ) -> FieldResult<Vec<scalar::Timestamp>> {
+        if !(1..=Self::MAX_OCCURRENCES).contains(&count) {
+            return Err(FieldError::new(
+                "'count' argument is out of range",
+                graphql_value!({
+                    "code": "VALUE_OUT_OF_RANGE",
+                    "min": 1,
+                    "max": (Self::MAX_OCCURRENCES),
+                }),
+            ));
+        }
+        let (Some(rule), Some(due_at)) = (self.recurrence.as_deref(), self.due_at) else {
+            return Ok(Vec::new());
+        };
+        let rule = rule.parse::<Recurrence>()?;
+        Ok(rule.occurrences_after(due_at, count as usize))
+    }
+    /// 所属清单
+    pub async fn list(&self, ctx: &graphql::Context) -> crate::error::Result<TodoList> {
+        ctx.list_loader
+            .load(self.list_id)
+            .await?
+            .ok_or_else(|| Error::not_found(TodoList::TYPE_NAME, self.list_id))
+    }
+    /// 父事项，顶层事项为 null
+    pub async fn parent(&self, ctx: &graphql::Context) -> crate::error::Result<Option<Todo>> {
+        match self.parent_id {
+            Some(parent_id) => ctx.todo_loader.load(parent_id).await,
+            None => Ok(None),
+        }
+    }
+    /// 直接子事项，参数与 `listTodos` 相同，`filter.parentId` 会被忽略
+    #[allow(clippy::too_many_arguments)]
+    pub async fn children(
+        &self,
+        executor: &Executor<'_, '_, graphql::Context, graphql::CustomScalarValue>,
+        ctx: &graphql::Context,
+        first: Option<i32>,
+        after: Option<relay::EncodedCursor>,
+        last: Option<i32>,
+        before: Option<relay::EncodedCursor>,
+        filter: Option<TodoFilter>,
+        order_by: Option<Vec<TodoOrder>>,
+    ) -> FieldResult<relay::Connection<Todo>> {
+        let pagination = relay::Pagination::new(first, after, last, before)?;
+        let filter = TodoFilter {
+            parent_id: Some(self.id()),
+            ..filter.unwrap_or_default()
+        };
+        let conn = relay::Connection::new(
+            executor,
+            pagination,
+            filter.clone(),
+            order_by.unwrap_or_default(),
+            async |pag, filter, order: &Vec<TodoOrder>| {
+                ctx.todo_repo.list_todos(pag, filter, order).await
+            },
+            async |filter| ctx.todo_repo.total(filter).await,
+        )
+        .await?
+        .load_fields(executor, async || ctx.todo_repo.counts(&filter).await)
+        .await?;
+        Ok(conn)
+    }
+    /// 从根事项到直接父事项的祖先链，顶层事项为空列表
+    pub async fn ancestors(&self, ctx: &graphql::Context) -> crate::error::Result<Vec<Todo>> {
+        match self.parent_id {
+            Some(_) => ctx.todo_repo.ancestors(self.id).await,
+            None => Ok(Vec::new()),
+        }
+    }
+    /// 所有层级的子孙事项数
+    pub async fn descendant_count(&self, ctx: &graphql::Context) -> crate::error::Result<i32> {
+        let count = ctx.descendant_count_loader.load(self.id).await?;
+        Ok(count.unwrap_or_default())
+    }
+    /// 事项上的标签
+    #[allow(clippy::too_many_arguments)]
+    pub async fn tags(
+        &self,
+        executor: &Executor<'_, '_, graphql::Context, graphql::CustomScalarValue>,
+        ctx: &graphql::Context,
+        first: Option<i32>,
+        after: Option<relay::EncodedCursor>,
+        last: Option<i32>,
+        before: Option<relay::EncodedCursor>,
+        order_by: Option<Vec<TagOrder>>,
+    ) -> FieldResult<relay::Connection<Tag>> {
+        let pagination = relay::Pagination::new(first, after, last, before)?;
+        let conn = relay::Connection::new(
+            executor,
+            pagination,
+            Some(self.id),
+            order_by.unwrap_or_default(),
+            async |pag, todo, order: &Vec<TagOrder>| {
+                ctx.tag_repo.list_tags(pag, *todo, order).await
+            },
+            async |todo| ctx.tag_repo.total(*todo).await,
+        )
+        .await?;
+        Ok(conn)
+    }
+}
+
+/// `TodoConnection` 上的统计字段，只在被查询时计算
+///
+/// 统计按完成状态拆分，因此忽略筛选条件中的 `done`，其余条件与 `totalCount` 相同
+#[derive(Debug, Clone, Default, ConnectionFields)]
+pub struct TodoCounts {
+    /// 未完成的事项数
+    pub(crate) active_count: i32,
+    /// 已完成的事项数
+    pub(crate) completed_count: i32,
+    /// 是否全部已完成，没有事项时为 false
+    pub(crate) all_completed: bool,
 }
 
-impl relay::ConnectionNode for Todo {
-    fn cursor(&self) -> relay::Cursor {
-        relay::Cursor::new(self.id, self.created_at)
+/// `listTodos` 的筛选条件，各字段之间为 AND 关系
+#[derive(Debug, Clone, Default, GraphQLInputObject)]
+#[graphql(scalar = graphql::CustomScalarValue)]
+pub struct TodoFilter {
+    /// 仅返回已完成（true）或未完成（false）的事项
+    pub(crate) done: Option<bool>,
+    /// 描述中包含该文本（不区分大小写）
+    pub(crate) description_contains: Option<String>,
+    /// 创建时间不早于该时间
+    pub(crate) created_since: Option<scalar::Timestamp>,
+    /// 创建时间早于该时间
+    pub(crate) created_before: Option<scalar::Timestamp>,
+    /// 截止时间早于该时间，没有截止时间的事项不会匹配
+    pub(crate) due_before: Option<scalar::Timestamp>,
+    /// 仅返回已逾期（true）或未逾期（false）的事项，逾期指未完成且已过截止时间
+    pub(crate) overdue: Option<bool>,
+    /// 仅返回这些 ID 对应的事项
+    pub(crate) ids: Option<Vec<relay::GlobalId>>,
+    /// 仅返回该清单中的事项
+    pub(crate) list_id: Option<relay::GlobalId>,
+    /// 仅返回该事项的直接子事项
+    pub(crate) parent_id: Option<relay::GlobalId>,
+    /// 按标签筛选
+    pub(crate) tags: Option<TodoTagFilter>,
+    /// 只查询回收站中的事项，仅供 `trash` 连接使用
+    #[graphql(ignore)]
+    pub(crate) trashed: bool,
+}
+
+/// `TodoFilter.tags` 的匹配方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, GraphQLEnum)]
+pub enum TagMatch {
+    /// 带有其中任一标签
+    #[default]
+    Any,
+    /// 带有其中全部标签
+    All,
+}
+
+#[derive(Debug, Clone, GraphQLInputObject)]
+#[graphql(scalar = graphql::CustomScalarValue)]
+pub struct TodoTagFilter {
+    /// 标签 ID，为空时 `ANY` 不匹配任何事项，`ALL` 匹配全部事项
+    pub(crate) ids: Vec<relay::GlobalId>,
+    #[graphql(default)]
+    pub(crate) mode: TagMatch,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, GraphQLEnum)]
+pub enum TodoOrderField {
+    CreatedAt,
+    Description,
+    Done,
+    UpdatedAt,
+    /// 手动排序的位置，通过 `moveTodo` 调整
+    Position,
+}
+
+impl TodoOrderField {
+    pub const fn column(self) -> &'static str {
+        match self {
+            Self::CreatedAt => "created_at",
+            Self::Description => "description",
+            Self::Done => "done",
+            Self::UpdatedAt => "updated_at",
+            Self::Position => "position",
+        }
+    }
+}
+
+/// `listTodos` 的排序键，多个排序键按顺序依次比较
+#[derive(Debug, Clone, GraphQLInputObject)]
+#[graphql(scalar = graphql::CustomScalarValue)]
+pub struct TodoOrder {
+    pub(crate) field: TodoOrderField,
+    #[graphql(default)]
+    pub(crate) direction: relay::OrderDirection,
+}
+
+impl relay::ConnectionOrder for TodoOrder {
+    const KEYS: &'static [&'static str] = &[
+        TodoOrderField::CreatedAt.column(),
+        TodoOrderField::Description.column(),
+        TodoOrderField::Done.column(),
+        TodoOrderField::UpdatedAt.column(),
+        TodoOrderField::Position.column(),
+    ];
+
+    fn signature(&self) -> String {
+        format!("{}:{}", self.field.column(), self.direction.as_sql())
+    }
+
+    fn keys(&self) -> Vec<&'static str> {
+        vec![self.field.column()]
     }
-    const CONNECTION_TYPE_NAME: &'static str = "TodoConnection";
-    const EDGE_TYPE_NAME: &'static str = "TodoEdge";
 }
@@ -0,0 +1,89 @@
+use std::time::Duration;
+
+use juniper::futures::{future, StreamExt};
+use serde::Serialize;
+use sqlx::SqlitePool;
+
+use crate::{
+    error::Result,
+    events::EventHub,
+    graphql::{relay, scalar},
+    models::todo::Todo,
+    repositories::TodoRepository,
+};
+
+/// 提醒到达时发给前端的事件名
+pub const REMINDER_EVENT: &str = "todo-reminder";
+/// 读取数据库失败后的重试间隔
+const RETRY_DELAY: Duration = Duration::from_secs(60);
+
+/// `todo-reminder` 事件的负载，时间为 Unix 秒
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Reminder {
+    /// 与 GraphQL 中 `Todo.id` 相同的全局 ID
+    pub id: String,
+    pub description: String,
+    pub due_at: Option<scalar::Timestamp>,
+    pub remind_at: Option<scalar::Timestamp>,
+}
+
+impl From<Todo> for Reminder {
+    fn from(todo: Todo) -> Self {
+        Self {
+            id: String::from(&relay::GlobalId::new(Todo::TYPE_NAME, todo.id)),
+            description: todo.description,
+            due_at: todo.due_at,
+            remind_at: todo.remind_at,
+        }
+    }
+}
+
+/// 提醒调度任务，在下一个提醒时间唤醒并通过 `notify` 发出提醒，直到 `events` 关闭
+///
+/// 启动时会先补发应用关闭期间错过的提醒；任何事项变更都会使它重新计算下一次唤醒时间
+pub async fn run_scheduler<F>(pool: SqlitePool, events: EventHub, notify: F)
+where
+    F: Fn(Reminder) + Send + 'static,
+{
+    let repo = TodoRepository::new(pool, events.clone());
+    // 先订阅再查询，避免错过两者之间发生的变更
+    let changes = events.subscribe(|_| Some(()));
+    let mut changes = std::pin::pin!(changes);
+    loop {
+        let wait = match fire_due_reminders(&repo, &notify).await {
+            Ok(wait) => wait,
+            Err(e) => {
+                eprintln!("Failed to process reminders: {e:?}");
+                Some(RETRY_DELAY)
+            }
+        };
+        let sleep = async {
+            match wait {
+                Some(wait) => tokio::time::sleep(wait).await,
+                None => future::pending().await,
+            }
+        };
+        tokio::select! {
+            _ = sleep => {}
+            change = changes.next() => {
+                if change.is_none() {
+                    return;
+                }
+            }
+        }
+    }
+}
+
+/// 发出所有已到期的提醒，返回距离下一个提醒的时间，没有待发出的提醒时返回 `None`
+async fn fire_due_reminders<F>(repo: &TodoRepository, notify: &F) -> Result<Option<Duration>>
+where
+    F: Fn(Reminder),
+{
+    for todo in repo.take_due_reminders().await? {
+        notify(todo.into());
+    }
+    let now = chrono::Utc::now().timestamp();
+    let next = repo.next_reminder_at().await?;
+    Ok(next.map(|at| Duration::from_secs((i64::from(at) - now).max(0) as u64)))
+}
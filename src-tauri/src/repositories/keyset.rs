@@ -0,0 +1,245 @@
+use std::fmt::Write;
+
+use sqlx::{sqlite::SqliteArguments, Arguments, QueryBuilder, Sqlite};
+
+use crate::error::{Error, Result};
+use crate::graphql::relay::{Cursor, CursorValue, OrderDirection, Pagination};
+
+/// 构造一页数据的查询：在 `query`（`SELECT ... FROM ...`）后追加 `conditions` 与游标条件、
+/// ORDER BY 子句和 LIMIT/OFFSET，`arguments` 需已包含 `conditions` 引用的参数
+pub fn paginate<'q>(
+    mut query: String,
+    mut conditions: Vec<String>,
+    mut arguments: SqliteArguments<'q>,
+    keys: &[(&str, OrderDirection)],
+    pag: &Pagination,
+) -> Result<QueryBuilder<'q, Sqlite>> {
+    if let Some(after) = pag.after.as_ref() {
+        conditions.push(keyset_condition(keys, after, false, &mut arguments)?);
+    } else if let Some(before) = pag.before.as_ref() {
+        conditions.push(keyset_condition(keys, before, true, &mut arguments)?);
+    }
+    if !conditions.is_empty() {
+        write!(query, " WHERE {}", conditions.join(" AND "))?;
+    }
+    write!(
+        query,
+        " ORDER BY {} LIMIT ?{} OFFSET ?{}",
+        order_by_clause(keys, pag.last.is_some()),
+        arguments.len() + 1,
+        arguments.len() + 2
+    )?;
+    arguments.add(pag.fetch_limit()).unwrap();
+    arguments.add(pag.offset()).unwrap();
+    Ok(QueryBuilder::with_arguments(query, arguments))
+}
+
+/// 生成 ORDER BY 子句，末尾追加 `id` 作为决胜键；`reverse` 为 true 时所有方向取反（用于 `last`）
+pub fn order_by_clause(keys: &[(&str, OrderDirection)], reverse: bool) -> String {
+    let direction = |d: OrderDirection| if reverse { d.reverse() } else { d };
+    keys.iter()
+        .map(|(column, d)| format!("{column} {}", direction(*d).as_sql()))
+        .chain([format!("id {}", direction(OrderDirection::Asc).as_sql())])
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// 生成键集分页条件，`before` 为 false 时取排在游标之后的记录，否则取之前的记录
+///
+/// 对于排序键 `k1, k2, ..., id` 展开为
+/// `(k1 > v1) OR (k1 = v1 AND k2 > v2) OR ... OR (k1 = v1 AND ... AND id > id0)`，
+/// 其中每一列的比较符由该列的排序方向决定，因此可以支持混合方向的排序
+pub fn keyset_condition(
+    keys: &[(&str, OrderDirection)],
+    cursor: &Cursor,
+    before: bool,
+    arguments: &mut SqliteArguments<'_>,
+) -> Result<String> {
+    if cursor.values.len() != keys.len() {
+        return Err(Error::Validation(
+            "Cursor does not match the ordering".to_string(),
+        ));
+    }
+    let mut columns = Vec::with_capacity(keys.len() + 1);
+    for ((column, direction), value) in keys.iter().zip(&cursor.values) {
+        match value {
+            CursorValue::Boolean(v) => arguments.add(*v).unwrap(),
+            CursorValue::Integer(v) => arguments.add(*v).unwrap(),
+            CursorValue::Float(v) => arguments.add(*v).unwrap(),
+            CursorValue::Text(v) => arguments.add(v.clone()).unwrap(),
+        }
+        columns.push((*column, *direction, arguments.len()));
+    }
+    arguments.add(cursor.id).unwrap();
+    columns.push(("id", OrderDirection::Asc, arguments.len()));
+
+    let terms = (0..columns.len())
+        .map(|i| {
+            let (column, direction, index) = columns[i];
+            let operator = match (direction, before) {
+                (OrderDirection::Asc, false) | (OrderDirection::Desc, true) => ">",
+                (OrderDirection::Desc, false) | (OrderDirection::Asc, true) => "<",
+            };
+            let equals = columns[..i]
+                .iter()
+                .map(|(column, _, index)| format!("{column} = ?{index}"));
+            let term = equals
+                .chain([format!("{column} {operator} ?{index}")])
+                .collect::<Vec<_>>()
+                .join(" AND ");
+            format!("( {term} )")
+        })
+        .collect::<Vec<_>>();
+    Ok(format!("( {} )", terms.join(" OR ")))
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::Connection;
+
+    use super::*;
+    use crate::graphql::scalar;
+
+    const KEYS: [(&str, OrderDirection); 2] = [
+        ("done", OrderDirection::Asc),
+        ("created_at", OrderDirection::Desc),
+    ];
+
+    fn cursor(done: bool, created_at: i64, id: i64) -> Cursor {
+        Cursor::new(
+            "TodoConnection",
+            String::from("done:ASC,created_at:DESC"),
+            vec![CursorValue::Boolean(done), CursorValue::Integer(created_at)],
+            scalar::ID::from(id),
+        )
+    }
+
+    #[test]
+    fn order_by_appends_id() {
+        assert_eq!(
+            order_by_clause(&KEYS, false),
+            "done ASC, created_at DESC, id ASC"
+        );
+        assert_eq!(
+            order_by_clause(&KEYS, true),
+            "done DESC, created_at ASC, id DESC"
+        );
+        assert_eq!(order_by_clause(&[], false), "id ASC");
+    }
+
+    #[test]
+    fn condition_follows_each_direction() {
+        let mut arguments = SqliteArguments::default();
+        let after = keyset_condition(&KEYS, &cursor(false, 100, 7), false, &mut arguments).unwrap();
+        assert_eq!(
+            after,
+            "( ( done > ?1 ) OR ( done = ?1 AND created_at < ?2 ) \
+             OR ( done = ?1 AND created_at = ?2 AND id > ?3 ) )"
+        );
+        assert_eq!(arguments.len(), 3);
+
+        // 已有参数时从下一个序号开始编号，`before` 取反比较方向
+        let before = keyset_condition(&KEYS, &cursor(false, 100, 7), true, &mut arguments).unwrap();
+        assert_eq!(
+            before,
+            "( ( done < ?4 ) OR ( done = ?4 AND created_at > ?5 ) \
+             OR ( done = ?4 AND created_at = ?5 AND id < ?6 ) )"
+        );
+        assert_eq!(arguments.len(), 6);
+    }
+
+    #[test]
+    fn rejects_cursor_of_other_ordering() {
+        let mut arguments = SqliteArguments::default();
+        let err = keyset_condition(&KEYS[..1], &cursor(false, 100, 7), false, &mut arguments)
+            .unwrap_err();
+        assert!(matches!(err, Error::Validation(_)));
+        assert_eq!(arguments.len(), 0);
+    }
+
+    /// 逐页向后、向前翻页，结果应与一次取出全部记录的顺序一致且不重不漏
+    #[tokio::test]
+    async fn pages_through_rows() {
+        let mut conn = sqlx::SqliteConnection::connect("sqlite::memory:")
+            .await
+            .unwrap();
+        sqlx::query(
+            "CREATE TABLE todos ( id INTEGER PRIMARY KEY, done BOOLEAN, created_at INTEGER )",
+        )
+        .execute(&mut conn)
+        .await
+        .unwrap();
+        // 含有相同的排序键，需要靠 `id` 决胜
+        for (id, done, created_at) in [
+            (1, false, 10),
+            (2, true, 30),
+            (3, false, 30),
+            (4, false, 10),
+            (5, true, 20),
+            (6, false, 20),
+            (7, true, 30),
+        ] {
+            sqlx::query("INSERT INTO todos VALUES ( ?1, ?2, ?3 )")
+                .bind(id)
+                .bind(done)
+                .bind(created_at)
+                .execute(&mut conn)
+                .await
+                .unwrap();
+        }
+        let expected = [3, 6, 1, 4, 2, 7, 5];
+
+        async fn page(
+            conn: &mut sqlx::SqliteConnection,
+            pag: &Pagination,
+        ) -> Vec<(i64, bool, i64)> {
+            let mut query = paginate(
+                "SELECT id, done, created_at FROM todos".into(),
+                Vec::new(),
+                SqliteArguments::default(),
+                &KEYS,
+                pag,
+            )
+            .unwrap();
+            let mut rows = query.build_query_as().fetch_all(conn).await.unwrap();
+            rows.truncate(pag.limit() as usize);
+            rows
+        }
+
+        let mut forward = Vec::new();
+        let mut after = None;
+        loop {
+            let pag = Pagination {
+                first: Some(2),
+                after: after.take(),
+                ..Default::default()
+            };
+            let rows = page(&mut conn, &pag).await;
+            let Some(&(id, done, created_at)) = rows.last() else {
+                break;
+            };
+            forward.extend(rows.iter().map(|row| row.0));
+            after = Some(cursor(done, created_at, id));
+        }
+        assert_eq!(forward, expected);
+
+        // `last` 按相反方向排序取数，结果需要再反转
+        let mut backward = Vec::new();
+        let mut before = None;
+        loop {
+            let pag = Pagination {
+                last: Some(3),
+                before: before.take(),
+                ..Default::default()
+            };
+            let rows = page(&mut conn, &pag).await;
+            let Some(&(id, done, created_at)) = rows.last() else {
+                break;
+            };
+            backward.extend(rows.iter().map(|row| row.0));
+            before = Some(cursor(done, created_at, id));
+        }
+        backward.reverse();
+        assert_eq!(backward, expected);
+    }
+}
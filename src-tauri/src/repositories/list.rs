@@ -0,0 +1,170 @@
+use std::collections::HashMap;
+
+use crate::{
+    error::{Error, Result},
+    graphql::{loader::BatchFn, relay, scalar},
+    models::list::{TodoList, TodoListOrder},
+    repositories::keyset,
+};
+
+#[derive(Clone)]
+pub struct ListRepository {
+    pool: sqlx::SqlitePool,
+}
+
+impl ListRepository {
+    pub fn new(pool: sqlx::SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn create_list(&self, name: String, color: Option<String>) -> Result<TodoList> {
+        let name = Self::normalize_name(&name)?;
+        let color = color.as_deref().map(Self::normalize_color).transpose()?;
+        let list = sqlx::query_as::<_, TodoList>(
+            r#"
+            INSERT INTO lists ( name, color )
+            VALUES ( ?1, ?2 )
+            RETURNING id, name, color, archived, created_at
+            "#,
+        )
+        .bind(name)
+        .bind(color)
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(list)
+    }
+
+    /// 为 `None` 的字段保持不变
+    pub async fn update_list(
+        &self,
+        id: scalar::ID,
+        name: Option<String>,
+        color: Option<String>,
+        archived: Option<bool>,
+    ) -> Result<TodoList> {
+        let name = name.as_deref().map(Self::normalize_name).transpose()?;
+        let color = color.as_deref().map(Self::normalize_color).transpose()?;
+        let list = sqlx::query_as::<_, TodoList>(
+            r#"
+            UPDATE lists
+            SET name = COALESCE(?2, name),
+                color = COALESCE(?3, color),
+                archived = COALESCE(?4, archived)
+            WHERE id = ?1
+            RETURNING id, name, color, archived, created_at
+            "#,
+        )
+        .bind(id)
+        .bind(name)
+        .bind(color)
+        .bind(archived)
+        .fetch_optional(&self.pool)
+        .await?;
+        list.ok_or_else(|| Error::not_found(TodoList::TYPE_NAME, id))
+    }
+
+    pub async fn find_lists(&self, ids: &[scalar::ID]) -> Result<Vec<TodoList>> {
+        if ids.is_empty() {
+            return Ok(Vec::new());
+        }
+        let mut query = sqlx::QueryBuilder::<sqlx::Sqlite>::new(
+            "SELECT id, name, color, archived, created_at FROM lists WHERE id IN ( ",
+        );
+        let mut separated = query.separated(", ");
+        for id in ids {
+            separated.push_bind(*id);
+        }
+        separated.push_unseparated(" )");
+        let lists = query
+            .build_query_as::<TodoList>()
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(lists)
+    }
+
+    /// `archived` 为 `None` 时不按归档状态筛选
+    pub async fn list_lists(
+        &self,
+        pag: &relay::Pagination,
+        archived: Option<bool>,
+        order: &[TodoListOrder],
+    ) -> Result<Vec<TodoList>> {
+        let mut arguments = sqlx::sqlite::SqliteArguments::default();
+        let conditions = Self::archived_condition(archived, &mut arguments);
+        let keys = order
+            .iter()
+            .map(|it| (it.field.column(), it.direction))
+            .collect::<Vec<_>>();
+
+        let mut query = keyset::paginate(
+            "SELECT id, name, color, archived, created_at FROM lists".into(),
+            conditions,
+            arguments,
+            &keys,
+            pag,
+        )?;
+        let lists = query
+            .build_query_as::<TodoList>()
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(lists)
+    }
+
+    pub async fn total(&self, archived: Option<bool>) -> Result<i32> {
+        let mut query = String::from("SELECT COUNT(*) FROM lists ");
+        let mut arguments = sqlx::sqlite::SqliteArguments::default();
+        let conditions = Self::archived_condition(archived, &mut arguments);
+        if !conditions.is_empty() {
+            query.push_str("WHERE ");
+            query.push_str(&conditions.join(" AND "));
+        }
+
+        let mut query = sqlx::QueryBuilder::<sqlx::Sqlite>::with_arguments(query, arguments);
+        let total = query
+            .build_query_scalar::<i64>()
+            .fetch_one(&self.pool)
+            .await?;
+        Ok(total as i32)
+    }
+
+    fn archived_condition(
+        archived: Option<bool>,
+        arguments: &mut sqlx::sqlite::SqliteArguments<'_>,
+    ) -> Vec<String> {
+        use sqlx::Arguments;
+
+        let mut conditions = Vec::new();
+        if let Some(archived) = archived {
+            conditions.push(format!("archived = ?{}", arguments.len() + 1));
+            arguments.add(archived).unwrap();
+        }
+        conditions
+    }
+
+    /// 去掉首尾空白，空名称视为无效
+    fn normalize_name(name: &str) -> Result<&str> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(Error::Validation("List name must not be empty".to_string()));
+        }
+        Ok(name)
+    }
+
+    /// 颜色统一保存为小写的 `#rrggbb`
+    fn normalize_color(color: &str) -> Result<String> {
+        let hex = color.strip_prefix('#').unwrap_or_default();
+        if hex.len() != 6 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(Error::Validation(format!(
+                "Invalid color '{color}', expected #RRGGBB"
+            )));
+        }
+        Ok(color.to_ascii_lowercase())
+    }
+}
+
+impl BatchFn<scalar::ID, TodoList> for ListRepository {
+    async fn load(&self, keys: &[scalar::ID]) -> Result<HashMap<scalar::ID, TodoList>> {
+        let lists = self.find_lists(keys).await?;
+        Ok(lists.into_iter().map(|list| (list.id, list)).collect())
+    }
+}
@@ -1,3 +1,10 @@
+mod keyset;
+mod list;
+mod operation;
+mod tag;
 mod todo;
 
+pub use list::ListRepository;
+pub use operation::OperationRepository;
+pub use tag::TagRepository;
 pub use todo::TodoRepository;
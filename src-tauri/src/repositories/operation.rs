@@ -0,0 +1,80 @@
+use std::collections::HashMap;
+
+use crate::{
+    error::Result,
+    graphql::{loader::BatchFn, relay, scalar},
+    models::operation::{Operation, OperationOrder},
+    repositories::keyset,
+};
+
+/// 操作日志的只读查询；记录、撤销和重做需要与事项的修改在同一事务中，由 `TodoRepository` 负责
+#[derive(Clone)]
+pub struct OperationRepository {
+    pool: sqlx::SqlitePool,
+}
+
+impl OperationRepository {
+    pub fn new(pool: sqlx::SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn find_operations(&self, ids: &[scalar::ID]) -> Result<Vec<Operation>> {
+        if ids.is_empty() {
+            return Ok(Vec::new());
+        }
+        let mut query = sqlx::QueryBuilder::<sqlx::Sqlite>::new(
+            "SELECT id, kind, changes, undone, created_at FROM operations WHERE id IN ( ",
+        );
+        let mut separated = query.separated(", ");
+        for id in ids {
+            separated.push_bind(*id);
+        }
+        separated.push_unseparated(" )");
+        let operations = query
+            .build_query_as::<Operation>()
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(operations)
+    }
+
+    pub async fn list_operations(
+        &self,
+        pag: &relay::Pagination,
+        order: &[OperationOrder],
+    ) -> Result<Vec<Operation>> {
+        let keys = order
+            .iter()
+            .map(|it| (it.field.column(), it.direction))
+            .collect::<Vec<_>>();
+
+        let mut query = keyset::paginate(
+            "SELECT id, kind, changes, undone, created_at FROM operations".into(),
+            Vec::new(),
+            Default::default(),
+            &keys,
+            pag,
+        )?;
+        let operations = query
+            .build_query_as::<Operation>()
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(operations)
+    }
+
+    pub async fn total(&self) -> Result<i32> {
+        let total = sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM operations")
+            .fetch_one(&self.pool)
+            .await?;
+        Ok(total as i32)
+    }
+}
+
+impl BatchFn<scalar::ID, Operation> for OperationRepository {
+    async fn load(&self, keys: &[scalar::ID]) -> Result<HashMap<scalar::ID, Operation>> {
+        let operations = self.find_operations(keys).await?;
+        Ok(operations
+            .into_iter()
+            .map(|operation| (operation.id, operation))
+            .collect())
+    }
+}
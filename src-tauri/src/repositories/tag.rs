@@ -0,0 +1,176 @@
+use std::collections::HashMap;
+
+use crate::{
+    error::{Error, Result},
+    graphql::{loader::BatchFn, relay, scalar},
+    models::tag::{Tag, TagOrder},
+    repositories::keyset,
+};
+
+/// 带有使用次数的标签，所有查询都从这里选择，使 `usage_count` 可以用于排序和游标条件
+///
+/// 回收站中的事项不计入使用次数
+const TAGS_WITH_USAGE: &str = r#"
+    WITH tags_with_usage AS (
+        SELECT id, name, created_at,
+               ( SELECT COUNT(*) FROM todo_tags
+                 JOIN todos ON todos.id = todo_tags.todo_id
+                 WHERE todo_tags.tag_id = tags.id AND todos.deleted_at IS NULL ) AS usage_count
+        FROM tags
+    )
+    SELECT id, name, created_at, usage_count FROM tags_with_usage "#;
+
+#[derive(Clone)]
+pub struct TagRepository {
+    pool: sqlx::SqlitePool,
+}
+
+impl TagRepository {
+    pub fn new(pool: sqlx::SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn create_tag(&self, name: String) -> Result<Tag> {
+        let name = Self::normalize_name(&name)?;
+        let tag = sqlx::query_as::<_, Tag>(
+            r#"
+            INSERT INTO tags ( name )
+            VALUES ( ?1 )
+            RETURNING id, name, created_at, 0 AS usage_count
+            "#,
+        )
+        .bind(name)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| Self::duplicate_name(e.into(), name))?;
+        Ok(tag)
+    }
+
+    pub async fn rename_tag(&self, id: scalar::ID, name: String) -> Result<Tag> {
+        let name = Self::normalize_name(&name)?;
+        let tag = sqlx::query_as::<_, Tag>(
+            r#"
+            UPDATE tags
+            SET name = ?2
+            WHERE id = ?1
+            RETURNING id, name, created_at,
+                      ( SELECT COUNT(*) FROM todo_tags
+                        JOIN todos ON todos.id = todo_tags.todo_id
+                        WHERE todo_tags.tag_id = tags.id AND todos.deleted_at IS NULL ) AS usage_count
+            "#,
+        )
+        .bind(id)
+        .bind(name)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| Self::duplicate_name(e.into(), name))?;
+        tag.ok_or_else(|| Error::not_found(Tag::TYPE_NAME, id))
+    }
+
+    /// 删除标签及其与事项的关联，事项本身不受影响
+    pub async fn delete_tag(&self, id: scalar::ID) -> Result<scalar::ID> {
+        let removed = sqlx::query_scalar::<_, scalar::ID>(
+            r#"
+            DELETE FROM tags WHERE id = ?1
+            RETURNING id
+            "#,
+        )
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?;
+        removed.ok_or_else(|| Error::not_found(Tag::TYPE_NAME, id))
+    }
+
+    pub async fn find_tags(&self, ids: &[scalar::ID]) -> Result<Vec<Tag>> {
+        if ids.is_empty() {
+            return Ok(Vec::new());
+        }
+        let mut query = sqlx::QueryBuilder::<sqlx::Sqlite>::new(TAGS_WITH_USAGE);
+        query.push("WHERE id IN ( ");
+        let mut separated = query.separated(", ");
+        for id in ids {
+            separated.push_bind(*id);
+        }
+        separated.push_unseparated(" )");
+        let tags = query.build_query_as::<Tag>().fetch_all(&self.pool).await?;
+        Ok(tags)
+    }
+
+    /// 列出标签，传入 `todo` 时只列出该事项上的标签
+    pub async fn list_tags(
+        &self,
+        pag: &relay::Pagination,
+        todo: Option<scalar::ID>,
+        order: &[TagOrder],
+    ) -> Result<Vec<Tag>> {
+        let mut arguments = sqlx::sqlite::SqliteArguments::default();
+        let conditions = Self::todo_condition(todo, &mut arguments);
+        let keys = order
+            .iter()
+            .map(|it| (it.field.column(), it.direction))
+            .collect::<Vec<_>>();
+
+        let mut query =
+            keyset::paginate(TAGS_WITH_USAGE.into(), conditions, arguments, &keys, pag)?;
+        let tags = query.build_query_as::<Tag>().fetch_all(&self.pool).await?;
+        Ok(tags)
+    }
+
+    pub async fn total(&self, todo: Option<scalar::ID>) -> Result<i32> {
+        let mut query = String::from("SELECT COUNT(*) FROM tags ");
+        let mut arguments = sqlx::sqlite::SqliteArguments::default();
+        let conditions = Self::todo_condition(todo, &mut arguments);
+        if !conditions.is_empty() {
+            query.push_str("WHERE ");
+            query.push_str(&conditions.join(" AND "));
+        }
+
+        let mut query = sqlx::QueryBuilder::<sqlx::Sqlite>::with_arguments(query, arguments);
+        let total = query
+            .build_query_scalar::<i64>()
+            .fetch_one(&self.pool)
+            .await?;
+        Ok(total as i32)
+    }
+
+    fn todo_condition(
+        todo: Option<scalar::ID>,
+        arguments: &mut sqlx::sqlite::SqliteArguments<'_>,
+    ) -> Vec<String> {
+        use sqlx::Arguments;
+
+        let mut conditions = Vec::new();
+        if let Some(todo) = todo {
+            conditions.push(format!(
+                "id IN ( SELECT tag_id FROM todo_tags WHERE todo_id = ?{} )",
+                arguments.len() + 1
+            ));
+            arguments.add(todo).unwrap();
+        }
+        conditions
+    }
+
+    /// 去掉首尾空白，空名称视为无效
+    fn normalize_name(name: &str) -> Result<&str> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(Error::Validation("Tag name must not be empty".to_string()));
+        }
+        Ok(name)
+    }
+
+    /// 名称唯一约束冲突时给出可读的错误信息
+    fn duplicate_name(e: Error, name: &str) -> Error {
+        match e {
+            Error::Conflict(_) => Error::Conflict(format!("Tag '{name}' already exists")),
+            e => e,
+        }
+    }
+}
+
+impl BatchFn<scalar::ID, Tag> for TagRepository {
+    async fn load(&self, keys: &[scalar::ID]) -> Result<HashMap<scalar::ID, Tag>> {
+        let tags = self.find_tags(keys).await?;
+        Ok(tags.into_iter().map(|tag| (tag.id, tag)).collect())
+    }
+}
@@ -1,146 +1,1121 @@
-use anyhow::Ok;
+use std::collections::HashMap;
+
+use anyhow::anyhow;
 
 use crate::{
-    graphql::{relay, scalar},
-    models::todo::Todo,
+    error::{Error, Result},
+    events::{EventHub, TodoEvent},
+    graphql::{loader::BatchFn, relay, scalar},
+    models::{
+        list::TodoList,
+        operation::{Operation, OperationKind, TodoChange, TodoSnapshot},
+        recurrence::Recurrence,
+        search::SearchMatch,
+        tag::Tag,
+        todo::{TagMatch, Todo, TodoCounts, TodoFilter, TodoOrder, TodoTagFilter},
+    },
+    repositories::keyset,
 };
 
+/// 查询和 `RETURNING` 返回的事项列，与 `Todo` 的字段一一对应
+const TODO_COLUMNS: &str = "id, description, done, created_at, updated_at, position, due_at, remind_at, list_id, parent_id, recurrence, deleted_at";
+/// 新事项以及重排后相邻两项之间的位置间隔
+const POSITION_GAP: i64 = 1024;
+/// 操作日志保留的最近操作数，更早的操作无法再撤销
+const MAX_OPERATIONS: i64 = 1000;
+
+#[derive(Clone)]
 pub struct TodoRepository {
     pool: sqlx::SqlitePool,
+    events: EventHub,
 }
 
 impl TodoRepository {
-    pub fn new(pool: sqlx::SqlitePool) -> Self {
-        Self { pool }
+    pub fn new(pool: sqlx::SqlitePool, events: EventHub) -> Self {
+        Self { pool, events }
     }
-    pub async fn add_todo(&self, description: String) -> anyhow::Result<scalar::ID> {
-        let mut conn = self.pool.acquire().await?;
+    /// 清单不存在时返回 `NotFound`
+    pub async fn add_todo(&self, list_id: scalar::ID, description: String) -> Result<Todo> {
+        let mut tx = self.pool.begin().await?;
 
-        let id = sqlx::query!(
+        let todo = sqlx::query_as::<_, Todo>(&format!(
             r#"
-            INSERT INTO todos ( description )
-            VALUES ( ?1 )
-            "#,
-            description
+            INSERT INTO todos ( description, position, list_id )
+            SELECT ?1, COALESCE(( SELECT MAX(position) FROM todos ), 0) + ?2, id
+            FROM lists
+            WHERE id = ?3
+            RETURNING {TODO_COLUMNS}
+            "#
+        ))
+        .bind(description)
+        .bind(POSITION_GAP)
+        .bind(list_id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| Error::not_found(TodoList::TYPE_NAME, list_id))?;
+        Self::record_operation(
+            &mut tx,
+            OperationKind::AddTodo,
+            &[TodoChange::created(&todo)],
         )
-        .execute(&mut *conn)
+        .await?;
+        tx.commit().await?;
+
+        self.events.publish(TodoEvent::Added(todo.clone()));
+        Ok(todo)
+    }
+
+    /// `include_descendants` 为 true 时所有子孙事项一并修改，状态实际变化的子孙事项也会发布更新事件
+    ///
+    /// 重复事项被标记为完成时，在同一事务中创建下一次重复的事项
+    pub async fn complete_todo(
+        &self,
+        id: scalar::ID,
+        done: bool,
+        include_descendants: bool,
+    ) -> Result<Todo> {
+        let mut tx = self.pool.begin().await?;
+        let was_done = sqlx::query_scalar::<_, bool>(
+            "SELECT done FROM todos WHERE id = ?1 AND deleted_at IS NULL",
+        )
+        .bind(id)
+        .fetch_optional(&mut *tx)
         .await?
-        .last_insert_rowid();
-        Ok(id.into())
+        .ok_or_else(|| Error::not_found(Todo::TYPE_NAME, id))?;
+        let mut todos = sqlx::query_as::<_, Todo>(&format!(
+            r#"
+            WITH RECURSIVE subtree(id) AS (
+                SELECT ?1
+                UNION ALL
+                SELECT todos.id FROM todos JOIN subtree ON todos.parent_id = subtree.id
+                WHERE ?3 AND todos.deleted_at IS NULL
+            )
+            UPDATE todos
+            SET done = ?2, updated_at = UNIXEPOCH()
+            WHERE id IN subtree AND ( id = ?1 OR done <> ?2 ) AND deleted_at IS NULL
+            RETURNING {TODO_COLUMNS}
+            "#
+        ))
+        .bind(id)
+        .bind(done)
+        .bind(include_descendants)
+        .fetch_all(&mut *tx)
+        .await?;
+        let mut added = Vec::new();
+        let mut changes = Vec::new();
+        for todo in todos.iter_mut() {
+            let was_done = if todo.id == id { was_done } else { !done };
+            let before = TodoSnapshot {
+                done: was_done,
+                ..TodoSnapshot::from(&*todo)
+            };
+            if done && !was_done {
+                added.extend(Self::insert_next_occurrence(&mut tx, todo).await?);
+            }
+            changes.push(TodoChange::new(before, todo));
+        }
+        changes.extend(added.iter().map(TodoChange::created));
+        Self::record_operation(&mut tx, OperationKind::CompleteTodo, &changes).await?;
+        tx.commit().await?;
+
+        for todo in added {
+            self.events.publish(TodoEvent::Added(todo));
+        }
+        let (todo, descendants): (Vec<_>, Vec<_>) = todos.into_iter().partition(|it| it.id == id);
+        for todo in descendants {
+            self.events.publish(TodoEvent::Updated(todo));
+        }
+        self.publish_updated(id, todo.into_iter().next())
+    }
+
+    /// 只修改 `list_id` 清单中的事项，返回状态实际发生变化的事项；清单不存在时返回 `NotFound`
+    ///
+    /// 与 `complete_todo` 相同，被标记为完成的重复事项会创建下一次重复的事项
+    pub async fn toggle_all(&self, list_id: scalar::ID, done: bool) -> Result<Vec<Todo>> {
+        let mut tx = self.pool.begin().await?;
+        Self::ensure_list(&mut tx, list_id).await?;
+        let mut todos = sqlx::query_as::<_, Todo>(&format!(
+            r#"
+            UPDATE todos
+            SET done = ?1, updated_at = UNIXEPOCH()
+            WHERE done <> ?1 AND list_id = ?2 AND deleted_at IS NULL
+            RETURNING {TODO_COLUMNS}
+            "#
+        ))
+        .bind(done)
+        .bind(list_id)
+        .fetch_all(&mut *tx)
+        .await?;
+        let mut added = Vec::new();
+        let mut changes = Vec::new();
+        for todo in todos.iter_mut() {
+            let before = TodoSnapshot {
+                done: !done,
+                ..TodoSnapshot::from(&*todo)
+            };
+            if done {
+                added.extend(Self::insert_next_occurrence(&mut tx, todo).await?);
+            }
+            changes.push(TodoChange::new(before, todo));
+        }
+        changes.extend(added.iter().map(TodoChange::created));
+        // 批量操作作为一条记录，撤销时整体恢复
+        Self::record_operation(&mut tx, OperationKind::ToggleAll, &changes).await?;
+        tx.commit().await?;
+
+        for todo in added {
+            self.events.publish(TodoEvent::Added(todo));
+        }
+        for todo in todos.iter() {
+            self.events.publish(TodoEvent::Updated(todo.clone()));
+        }
+        Ok(todos)
+    }
+
+    /// 将事项连同其子孙事项移入回收站，子孙事项同样会发布删除事件
+    pub async fn remove_todo(&self, id: scalar::ID) -> Result<scalar::ID> {
+        let mut tx = self.pool.begin().await?;
+        let todos = sqlx::query_as::<_, Todo>(&format!(
+            r#"
+            WITH RECURSIVE subtree(id) AS (
+                SELECT id FROM todos WHERE id = ?1 AND deleted_at IS NULL
+                UNION ALL
+                SELECT todos.id FROM todos JOIN subtree ON todos.parent_id = subtree.id
+                WHERE todos.deleted_at IS NULL
+            )
+            UPDATE todos
+            SET deleted_at = UNIXEPOCH()
+            WHERE id IN subtree
+            RETURNING {TODO_COLUMNS}
+            "#
+        ))
+        .bind(id)
+        .fetch_all(&mut *tx)
+        .await?;
+        if todos.is_empty() {
+            return Err(Error::not_found(Todo::TYPE_NAME, id));
+        }
+        Self::record_operation(&mut tx, OperationKind::RemoveTodo, &Self::trashed(&todos)).await?;
+        tx.commit().await?;
+
+        for todo in todos {
+            self.events.publish(TodoEvent::Removed(todo.id));
+        }
+        Ok(id)
     }
 
-    pub async fn complete_todo(&self, id: scalar::ID, done: bool) -> anyhow::Result<bool> {
-        let rows_affected = sqlx::query!(
+    /// 将 `list_id` 清单中的已完成事项移入回收站，返回这些事项的 ID；清单不存在时返回 `NotFound`
+    ///
+    /// 子事项总是随父事项一起移入回收站，因此还有未完成子孙事项的父事项会被保留
+    pub async fn clear_completed(&self, list_id: scalar::ID) -> Result<Vec<scalar::ID>> {
+        let mut tx = self.pool.begin().await?;
+        Self::ensure_list(&mut tx, list_id).await?;
+        let todos = sqlx::query_as::<_, Todo>(&format!(
             r#"
+            WITH RECURSIVE pending(id) AS (
+                SELECT parent_id FROM todos
+                WHERE done = FALSE AND parent_id IS NOT NULL AND deleted_at IS NULL
+                UNION
+                SELECT todos.parent_id FROM todos JOIN pending ON todos.id = pending.id
+                WHERE todos.parent_id IS NOT NULL
+            )
             UPDATE todos
-            SET done = ?2
+            SET deleted_at = UNIXEPOCH()
+            WHERE done = TRUE AND list_id = ?1 AND deleted_at IS NULL AND id NOT IN pending
+            RETURNING {TODO_COLUMNS}
+            "#
+        ))
+        .bind(list_id)
+        .fetch_all(&mut *tx)
+        .await?;
+        Self::record_operation(
+            &mut tx,
+            OperationKind::ClearCompleted,
+            &Self::trashed(&todos),
+        )
+        .await?;
+        tx.commit().await?;
+
+        let ids = todos.into_iter().map(|it| it.id).collect::<Vec<_>>();
+
+        if !ids.is_empty() {
+            self.events.publish(TodoEvent::Cleared(ids.clone()));
+        }
+        Ok(ids)
+    }
+
+    /// 撤销最近一次未被撤销的操作，返回该操作和受影响的事项；没有可撤销的操作时返回 `None`
+    ///
+    /// 彻底删除（清空回收站或自动清理）事项时，它们的变更已从操作日志中移除，不会被恢复
+    pub async fn undo(&self) -> Result<Option<(Operation, Vec<Todo>)>> {
+        self.replay(true).await
+    }
+
+    /// 重做最早一次被撤销的操作，返回值与 `undo` 相同
+    pub async fn redo(&self) -> Result<Option<(Operation, Vec<Todo>)>> {
+        self.replay(false).await
+    }
+
+    /// 撤销（`undo` 为 true）时写回操作前的状态，重做时写回操作后的状态
+    async fn replay(&self, undo: bool) -> Result<Option<(Operation, Vec<Todo>)>> {
+        let mut tx = self.pool.begin().await?;
+        let query = if undo {
+            r#"
+            SELECT id, kind, changes, undone, created_at FROM operations
+            WHERE undone = FALSE ORDER BY id DESC LIMIT 1
+            "#
+        } else {
+            r#"
+            SELECT id, kind, changes, undone, created_at FROM operations
+            WHERE undone = TRUE ORDER BY id LIMIT 1
+            "#
+        };
+        let Some(operation) = sqlx::query_as::<_, Operation>(query)
+            .fetch_optional(&mut *tx)
+            .await?
+        else {
+            return Ok(None);
+        };
+
+        let mut todos = Vec::new();
+        let mut events = Vec::new();
+        let mut trashed = Vec::new();
+        for change in operation.changes()? {
+            let (from, to) = if undo {
+                (&change.after, &change.before)
+            } else {
+                (&change.before, &change.after)
+            };
+            // 只写回这次操作改动过的字段，之后的其他修改（例如重复规则）保持不变
+            let todo = sqlx::query_as::<_, Todo>(&format!(
+                r#"
+                UPDATE todos
+                SET description = CASE WHEN ?3 THEN ?2 ELSE description END,
+                    done = CASE WHEN ?5 THEN ?4 ELSE done END,
+                    recurrence = CASE WHEN ?7 THEN ?6 ELSE recurrence END,
+                    deleted_at = CASE
+                        WHEN NOT ?9 THEN deleted_at
+                        WHEN ?8 THEN COALESCE(deleted_at, UNIXEPOCH())
+                    END,
+                    updated_at = UNIXEPOCH()
+                WHERE id = ?1
+                RETURNING {TODO_COLUMNS}
+                "#
+            ))
+            .bind(change.id)
+            .bind(&to.description)
+            .bind(from.description != to.description)
+            .bind(to.done)
+            .bind(from.done != to.done)
+            .bind(&to.recurrence)
+            .bind(from.recurrence != to.recurrence)
+            .bind(to.trashed)
+            .bind(from.trashed != to.trashed)
+            .fetch_optional(&mut *tx)
+            .await?;
+            let Some(todo) = todo else {
+                continue;
+            };
+            match (from.trashed, to.trashed) {
+                (false, true) => {
+                    trashed.push(todo.id);
+                    events.push(TodoEvent::Removed(todo.id));
+                }
+                (true, false) => events.push(TodoEvent::Added(todo.clone())),
+                _ if todo.deleted_at.is_none() => events.push(TodoEvent::Updated(todo.clone())),
+                _ => {}
+            }
+            todos.push(todo);
+        }
+        // 撤销添加时只有该事项被移入回收站，之后移到它下面的子事项移到顶层，
+        // 否则会在父事项被彻底删除时被级联删除
+        for todo in Self::detach_children(&mut tx, &trashed).await? {
+            events.push(TodoEvent::Updated(todo.clone()));
+            todos.push(todo);
+        }
+        let operation = sqlx::query_as::<_, Operation>(
+            r#"
+            UPDATE operations
+            SET undone = ?2
             WHERE id = ?1
+            RETURNING id, kind, changes, undone, created_at
             "#,
-            id,
-            done
         )
-        .execute(&self.pool)
+        .bind(operation.id)
+        .bind(undo)
+        .fetch_one(&mut *tx)
+        .await?;
+        tx.commit().await?;
+
+        for event in events {
+            self.events.publish(event);
+        }
+        Ok(Some((operation, todos)))
+    }
+
+    /// 记录一次可撤销的操作，并清空重做栈；没有实际变化时不记录
+    async fn record_operation(
+        conn: &mut sqlx::SqliteConnection,
+        kind: OperationKind,
+        changes: &[TodoChange],
+    ) -> Result<()> {
+        let changes = changes
+            .iter()
+            .filter(|it| it.before != it.after)
+            .collect::<Vec<_>>();
+        if changes.is_empty() {
+            return Ok(());
+        }
+        sqlx::query("DELETE FROM operations WHERE undone = TRUE")
+            .execute(&mut *conn)
+            .await?;
+        sqlx::query("INSERT INTO operations ( kind, changes ) VALUES ( ?1, ?2 )")
+            .bind(kind)
+            .bind(serde_json::to_string(&changes)?)
+            .execute(&mut *conn)
+            .await?;
+        sqlx::query("DELETE FROM operations WHERE id <= LAST_INSERT_ROWID() - ?1")
+            .bind(MAX_OPERATIONS)
+            .execute(&mut *conn)
+            .await?;
+        Ok(())
+    }
+
+    /// 将 `parents` 中仍未删除的子事项移到顶层，返回被移动的事项
+    async fn detach_children(
+        conn: &mut sqlx::SqliteConnection,
+        parents: &[scalar::ID],
+    ) -> Result<Vec<Todo>> {
+        if parents.is_empty() {
+            return Ok(Vec::new());
+        }
+        let mut query = sqlx::QueryBuilder::<sqlx::Sqlite>::new(
+            "UPDATE todos SET parent_id = NULL, updated_at = UNIXEPOCH() WHERE deleted_at IS NULL AND parent_id IN ( ",
+        );
+        let mut separated = query.separated(", ");
+        for id in parents {
+            separated.push_bind(*id);
+        }
+        separated.push_unseparated(format!(" ) RETURNING {TODO_COLUMNS}"));
+        let todos = query.build_query_as::<Todo>().fetch_all(conn).await?;
+        Ok(todos)
+    }
+
+    /// 刚移入回收站的事项对应的变更
+    fn trashed(todos: &[Todo]) -> Vec<TodoChange> {
+        todos
+            .iter()
+            .map(|todo| {
+                let before = TodoSnapshot {
+                    trashed: false,
+                    ..todo.into()
+                };
+                TodoChange::new(before, todo)
+            })
+            .collect()
+    }
+
+    /// 从回收站恢复事项，以及与它一起被移入回收站的子孙事项
+    ///
+    /// 父事项仍在回收站中时，恢复后的事项移到顶层
+    pub async fn restore_todo(&self, id: scalar::ID) -> Result<Todo> {
+        let mut tx = self.pool.begin().await?;
+        let deleted_at = sqlx::query_scalar::<_, scalar::Timestamp>(
+            "SELECT deleted_at FROM todos WHERE id = ?1 AND deleted_at IS NOT NULL",
+        )
+        .bind(id)
+        .fetch_optional(&mut *tx)
         .await?
-        .rows_affected();
+        .ok_or_else(|| Error::not_found(Todo::TYPE_NAME, id))?;
+        sqlx::query(
+            r#"
+            UPDATE todos
+            SET parent_id = NULL
+            WHERE id = ?1 AND parent_id IN ( SELECT id FROM todos WHERE deleted_at IS NOT NULL )
+            "#,
+        )
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+        let todos = sqlx::query_as::<_, Todo>(&format!(
+            r#"
+            WITH RECURSIVE subtree(id) AS (
+                SELECT ?1
+                UNION ALL
+                SELECT todos.id FROM todos JOIN subtree ON todos.parent_id = subtree.id
+                WHERE todos.deleted_at = ?2
+            )
+            UPDATE todos
+            SET deleted_at = NULL
+            WHERE id IN subtree
+            RETURNING {TODO_COLUMNS}
+            "#
+        ))
+        .bind(id)
+        .bind(deleted_at)
+        .fetch_all(&mut *tx)
+        .await?;
+        tx.commit().await?;
 
-        Ok(rows_affected > 0)
+        for todo in todos.iter() {
+            self.events.publish(TodoEvent::Added(todo.clone()));
+        }
+        todos
+            .into_iter()
+            .find(|it| it.id == id)
+            .ok_or_else(|| Error::not_found(Todo::TYPE_NAME, id))
     }
 
-    pub async fn toggle_all(&self, done: bool) -> anyhow::Result<bool> {
-        let rows_affected = sqlx::query!(
+    /// 恢复回收站中的所有事项，传入 `list_id` 时只恢复该清单中的事项
+    ///
+    /// 与 `restore_todo` 相同，父事项仍在回收站中时，恢复后的事项移到顶层
+    pub async fn restore_all(&self, list_id: Option<scalar::ID>) -> Result<Vec<Todo>> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query(
             r#"
             UPDATE todos
-            SET done = ?1
-            WHERE done <> ?1
+            SET parent_id = NULL
+            WHERE deleted_at IS NOT NULL AND ( ?1 IS NULL OR list_id = ?1 )
+              AND parent_id IN (
+                  SELECT id FROM todos WHERE deleted_at IS NOT NULL AND list_id <> ?1
+              )
             "#,
-            done
         )
-        .execute(&self.pool)
-        .await?
-        .rows_affected();
+        .bind(list_id)
+        .execute(&mut *tx)
+        .await?;
+        let todos = sqlx::query_as::<_, Todo>(&format!(
+            r#"
+            UPDATE todos
+            SET deleted_at = NULL
+            WHERE deleted_at IS NOT NULL AND ( ?1 IS NULL OR list_id = ?1 )
+            RETURNING {TODO_COLUMNS}
+            "#
+        ))
+        .bind(list_id)
+        .fetch_all(&mut *tx)
+        .await?;
+        tx.commit().await?;
 
-        Ok(rows_affected > 0)
+        for todo in todos.iter() {
+            self.events.publish(TodoEvent::Added(todo.clone()));
+        }
+        Ok(todos)
     }
 
-    pub async fn remove_todo(&self, id: scalar::ID) -> anyhow::Result<bool> {
-        let rows_affected = sqlx::query!(
+    /// 彻底删除回收站中的所有事项，返回被删除事项的 ID
+    pub async fn empty_trash(&self) -> Result<Vec<scalar::ID>> {
+        self.delete_trashed(None).await
+    }
+
+    /// 彻底删除在 `before` 之前移入回收站的事项，返回删除的行数
+    pub async fn purge_trash(&self, before: scalar::Timestamp) -> Result<u64> {
+        let ids = self.delete_trashed(Some(before)).await?;
+        Ok(ids.len() as u64)
+    }
+
+    /// 彻底删除回收站中的事项（传入 `before` 时只删除在此之前移入的），并从操作日志中去掉它们的变更
+    ///
+    /// 子事项会被级联删除，因此一并列出，使事件和操作日志覆盖所有被删除的事项
+    async fn delete_trashed(&self, before: Option<scalar::Timestamp>) -> Result<Vec<scalar::ID>> {
+        let mut tx = self.pool.begin().await?;
+        let ids = sqlx::query_scalar::<_, scalar::ID>(
             r#"
-            DELETE FROM todos WHERE id = ?1
+            WITH RECURSIVE purged(id) AS (
+                SELECT id FROM todos
+                WHERE deleted_at IS NOT NULL AND ( ?1 IS NULL OR deleted_at < ?1 )
+                UNION
+                SELECT todos.id FROM todos JOIN purged ON todos.parent_id = purged.id
+            )
+            DELETE FROM todos
+            WHERE id IN purged
+            RETURNING id
             "#,
-            id
         )
-        .execute(&self.pool)
-        .await?
-        .rows_affected();
+        .bind(before)
+        .fetch_all(&mut *tx)
+        .await?;
+        Self::prune_operations(&mut tx, &ids).await?;
+        tx.commit().await?;
 
-        Ok(rows_affected > 0)
+        if !ids.is_empty() {
+            self.events.publish(TodoEvent::Cleared(ids.clone()));
+        }
+        Ok(ids)
     }
 
-    pub async fn clear_completed(&self) -> anyhow::Result<bool> {
-        let rows_affected = sqlx::query!(
+    /// 从操作日志中去掉 `ids` 对应事项的变更，只涉及这些事项的操作整条删除
+    ///
+    /// 这些事项已被彻底删除，留在日志中的变更无法撤销或重做，只会让 `history` 显示失效的操作
+    async fn prune_operations(conn: &mut sqlx::SqliteConnection, ids: &[scalar::ID]) -> Result<()> {
+        if ids.is_empty() {
+            return Ok(());
+        }
+        let ids = serde_json::to_string(&ids.iter().map(|&id| i64::from(id)).collect::<Vec<_>>())?;
+        sqlx::query(
             r#"
-            DELETE FROM todos WHERE done = TRUE
+            UPDATE operations
+            SET changes = (
+                SELECT json_group_array(json(value)) FROM (
+                    SELECT value FROM json_each(operations.changes)
+                    WHERE json_extract(value, '$.id') NOT IN ( SELECT value FROM json_each(?1) )
+                    ORDER BY key
+                )
+            )
+            WHERE EXISTS (
+                SELECT 1 FROM json_each(operations.changes)
+                WHERE json_extract(value, '$.id') IN ( SELECT value FROM json_each(?1) )
+            )
             "#,
         )
-        .execute(&self.pool)
-        .await?
-        .rows_affected();
+        .bind(&ids)
+        .execute(&mut *conn)
+        .await?;
+        sqlx::query("DELETE FROM operations WHERE json_array_length(changes) = 0")
+            .execute(&mut *conn)
+            .await?;
+        Ok(())
+    }
 
-        Ok(rows_affected > 0)
+    pub async fn edit_todo(&self, id: scalar::ID, description: String) -> Result<Todo> {
+        let mut tx = self.pool.begin().await?;
+        let before = Self::find_todo(&mut tx, id)
+            .await?
+            .ok_or_else(|| Error::not_found(Todo::TYPE_NAME, id))?;
+        let todo = sqlx::query_as::<_, Todo>(&format!(
+            r#"
+            UPDATE todos
+            SET description = ?2, updated_at = UNIXEPOCH()
+            WHERE id = ?1 AND deleted_at IS NULL
+            RETURNING {TODO_COLUMNS}
+            "#
+        ))
+        .bind(id)
+        .bind(description)
+        .fetch_optional(&mut *tx)
+        .await?;
+        if let Some(todo) = todo.as_ref() {
+            let change = TodoChange::new(TodoSnapshot::from(&before), todo);
+            Self::record_operation(&mut tx, OperationKind::EditTodo, &[change]).await?;
+        }
+        tx.commit().await?;
+
+        self.publish_updated(id, todo)
+    }
+
+    /// 同时设置截止时间和提醒时间，`None` 表示清除；提醒时间变化后会重新提醒
+    /// 清除截止时间时一并清除重复规则，重复以截止时间为起点
+    pub async fn schedule_todo(
+        &self,
+        id: scalar::ID,
+        due_at: Option<scalar::Timestamp>,
+        remind_at: Option<scalar::Timestamp>,
+    ) -> Result<Todo> {
+        let todo = sqlx::query_as::<_, Todo>(&format!(
+            r#"
+            UPDATE todos
+            SET due_at = ?2,
+                remind_at = ?3,
+                reminded = CASE WHEN remind_at IS ?3 THEN reminded ELSE FALSE END,
+                recurrence = CASE WHEN ?2 IS NULL THEN NULL ELSE recurrence END,
+                updated_at = UNIXEPOCH()
+            WHERE id = ?1 AND deleted_at IS NULL
+            RETURNING {TODO_COLUMNS}
+            "#
+        ))
+        .bind(id)
+        .bind(due_at)
+        .bind(remind_at)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        self.publish_updated(id, todo)
     }
 
-    pub async fn edit_todo(&self, id: scalar::ID, description: String) -> anyhow::Result<bool> {
-        let rows_affected = sqlx::query!(
+    /// 设置或清除（`None`）重复规则，设置时事项必须已有截止时间
+    pub async fn set_recurrence(
+        &self,
+        id: scalar::ID,
+        recurrence: Option<Recurrence>,
+    ) -> Result<Todo> {
+        let mut tx = self.pool.begin().await?;
+        let todo = Self::find_todo(&mut tx, id)
+            .await?
+            .ok_or_else(|| Error::not_found(Todo::TYPE_NAME, id))?;
+        if recurrence.is_some() && todo.due_at.is_none() {
+            return Err(Error::Validation(
+                "A recurring todo must have a due date".to_string(),
+            ));
+        }
+        let todo = sqlx::query_as::<_, Todo>(&format!(
             r#"
             UPDATE todos
-            SET description = ?2
+            SET recurrence = ?2, updated_at = UNIXEPOCH()
             WHERE id = ?1
+            RETURNING {TODO_COLUMNS}
+            "#
+        ))
+        .bind(id)
+        .bind(recurrence.map(|it| it.to_string()))
+        .fetch_optional(&mut *tx)
+        .await?;
+        tx.commit().await?;
+
+        self.publish_updated(id, todo)
+    }
+
+    /// 为刚完成的重复事项创建下一次重复的事项，返回新事项
+    ///
+    /// 新事项继承描述、清单、父事项和标签，提醒时间与截止时间保持原来的间隔；
+    /// 重复规则随之转移到新事项上，因此重新打开并再次完成旧事项不会重复创建
+    async fn insert_next_occurrence(
+        conn: &mut sqlx::SqliteConnection,
+        todo: &mut Todo,
+    ) -> Result<Option<Todo>> {
+        let (Some(rule), Some(due_at)) = (todo.recurrence.as_deref(), todo.due_at) else {
+            return Ok(None);
+        };
+        let next = rule.parse::<Recurrence>()?.next_after(due_at);
+        sqlx::query("UPDATE todos SET recurrence = NULL WHERE id = ?1")
+            .bind(todo.id)
+            .execute(&mut *conn)
+            .await?;
+        todo.recurrence = None;
+        let Some((next_due_at, rule)) = next else {
+            return Ok(None);
+        };
+        let remind_at = todo.remind_at.map(|remind_at| {
+            scalar::Timestamp::from(
+                i64::from(next_due_at) - (i64::from(due_at) - i64::from(remind_at)),
+            )
+        });
+
+        let next = sqlx::query_as::<_, Todo>(
+            &format!(
+                r#"
+            INSERT INTO todos ( description, position, list_id, parent_id, due_at, remind_at, recurrence )
+            VALUES ( ?1, COALESCE(( SELECT MAX(position) FROM todos ), 0) + ?2, ?3, ?4, ?5, ?6, ?7 )
+            RETURNING {TODO_COLUMNS}
+            "#
+            ),
+        )
+        .bind(&todo.description)
+        .bind(POSITION_GAP)
+        .bind(todo.list_id)
+        .bind(todo.parent_id)
+        .bind(next_due_at)
+        .bind(remind_at)
+        .bind(rule.to_string())
+        .fetch_one(&mut *conn)
+        .await?;
+        sqlx::query(
+            r#"
+            INSERT INTO todo_tags ( todo_id, tag_id )
+            SELECT ?1, tag_id FROM todo_tags WHERE todo_id = ?2
             "#,
-            id,
-            description
         )
-        .execute(&self.pool)
-        .await?
-        .rows_affected();
+        .bind(next.id)
+        .bind(todo.id)
+        .execute(&mut *conn)
+        .await?;
+        Ok(Some(next))
+    }
 
-        Ok(rows_affected > 0)
+    /// 取出提醒时间已到、尚未提醒且未完成的事项，并标记为已提醒
+    ///
+    /// 应用关闭期间错过的提醒会在下次调用时一并返回；标记不发布更新事件，事项的字段并未变化
+    pub async fn take_due_reminders(&self) -> Result<Vec<Todo>> {
+        let todos = sqlx::query_as::<_, Todo>(
+            &format!(
+                r#"
+            UPDATE todos
+            SET reminded = TRUE
+            WHERE reminded = FALSE AND done = FALSE AND remind_at <= UNIXEPOCH() AND deleted_at IS NULL
+            RETURNING {TODO_COLUMNS}
+            "#
+            ),
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(todos)
     }
 
-    pub async fn list_todos(&self, pag: &relay::Pagination) -> anyhow::Result<Vec<Todo>> {
-        use sqlx::Arguments;
-        use std::fmt::Write;
+    /// 下一个待发出的提醒时间，没有待发出的提醒时返回 `None`
+    pub async fn next_reminder_at(&self) -> Result<Option<scalar::Timestamp>> {
+        let next = sqlx::query_scalar::<_, Option<scalar::Timestamp>>(
+            r#"
+            SELECT MIN(remind_at) FROM todos
+            WHERE reminded = FALSE AND done = FALSE AND deleted_at IS NULL
+            "#,
+        )
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(next)
+    }
 
-        let mut query = String::from("SELECT id, description, done, created_at FROM todos ");
-        let mut arguments = sqlx::sqlite::SqliteArguments::default();
+    /// 将事项连同其子孙事项移动到 `parent` 之下，`None` 表示移到顶层
+    ///
+    /// 子事项总是与父事项在同一个清单中；不能移动到自身或自己的子孙事项之下
+    pub async fn move_todo_under(
+        &self,
+        id: scalar::ID,
+        parent: Option<scalar::ID>,
+    ) -> Result<Todo> {
+        let mut tx = self.pool.begin().await?;
+        if let Some(parent) = parent {
+            let list_id = sqlx::query_scalar::<_, scalar::ID>(
+                "SELECT list_id FROM todos WHERE id = ?1 AND deleted_at IS NULL",
+            )
+            .bind(parent)
+            .fetch_optional(&mut *tx)
+            .await?
+            .ok_or_else(|| Error::not_found(Todo::TYPE_NAME, parent))?;
+            let cycle = sqlx::query_scalar::<_, bool>(
+                r#"
+                WITH RECURSIVE chain(id) AS (
+                    SELECT ?1
+                    UNION ALL
+                    SELECT todos.parent_id FROM todos JOIN chain ON todos.id = chain.id
+                    WHERE todos.parent_id IS NOT NULL
+                )
+                SELECT EXISTS ( SELECT 1 FROM chain WHERE id = ?2 )
+                "#,
+            )
+            .bind(parent)
+            .bind(id)
+            .fetch_one(&mut *tx)
+            .await?;
+            if cycle {
+                return Err(Error::Validation(
+                    "Cannot move a todo under itself or one of its descendants".to_string(),
+                ));
+            }
+            sqlx::query(
+                r#"
+                WITH RECURSIVE subtree(id) AS (
+                    SELECT ?1
+                    UNION ALL
+                    SELECT todos.id FROM todos JOIN subtree ON todos.parent_id = subtree.id
+                )
+                UPDATE todos
+                SET list_id = ?2
+                WHERE id IN subtree AND list_id <> ?2
+                "#,
+            )
+            .bind(id)
+            .bind(list_id)
+            .execute(&mut *tx)
+            .await?;
+        }
+        let todo = sqlx::query_as::<_, Todo>(&format!(
+            r#"
+            UPDATE todos
+            SET parent_id = ?2, updated_at = UNIXEPOCH()
+            WHERE id = ?1 AND deleted_at IS NULL
+            RETURNING {TODO_COLUMNS}
+            "#
+        ))
+        .bind(id)
+        .bind(parent)
+        .fetch_optional(&mut *tx)
+        .await?;
+        if todo.is_some() {
+            tx.commit().await?;
+        }
+
+        self.publish_updated(id, todo)
+    }
+
+    /// 从根事项到直接父事项的祖先链
+    pub async fn ancestors(&self, id: scalar::ID) -> Result<Vec<Todo>> {
+        let todos = sqlx::query_as::<_, Todo>(&format!(
+            r#"
+            WITH RECURSIVE ancestors(id, depth) AS (
+                SELECT parent_id, 1 FROM todos
+                WHERE id = ?1 AND parent_id IS NOT NULL AND deleted_at IS NULL
+                UNION ALL
+                SELECT todos.parent_id, ancestors.depth + 1
+                FROM todos JOIN ancestors ON todos.id = ancestors.id
+                WHERE todos.parent_id IS NOT NULL AND todos.deleted_at IS NULL
+            )
+            SELECT {TODO_COLUMNS}
+            FROM todos JOIN ancestors USING (id)
+            WHERE deleted_at IS NULL
+            ORDER BY ancestors.depth DESC
+            "#
+        ))
+        .bind(id)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(todos)
+    }
+
+    /// 批量统计子孙事项数，没有子事项的不出现在结果中
+    pub async fn descendant_counts(&self, ids: &[scalar::ID]) -> Result<HashMap<scalar::ID, i32>> {
+        if ids.is_empty() {
+            return Ok(HashMap::new());
+        }
+        let mut query = sqlx::QueryBuilder::<sqlx::Sqlite>::new(
+            r#"
+            WITH RECURSIVE descendants(root, id) AS (
+                SELECT parent_id, id FROM todos WHERE deleted_at IS NULL AND parent_id IN ( "#,
+        );
+        let mut separated = query.separated(", ");
+        for id in ids {
+            separated.push_bind(*id);
+        }
+        separated.push_unseparated(
+            r#" )
+                UNION ALL
+                SELECT descendants.root, todos.id
+                FROM todos JOIN descendants ON todos.parent_id = descendants.id
+                WHERE todos.deleted_at IS NULL
+            )
+            SELECT root, COUNT(*) FROM descendants GROUP BY root
+            "#,
+        );
+        let counts = query
+            .build_query_as::<(scalar::ID, i64)>()
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(counts
+            .into_iter()
+            .map(|(id, count)| (id, count as i32))
+            .collect())
+    }
 
-        if let Some(after) = pag.after.as_ref() {
-            write!(
-                query,
-                "WHERE (id, created_at) > ( ?{}, ?{} ) ",
-                arguments.len() + 1,
-                arguments.len() + 2
-            )?;
-            arguments.add(after.id).unwrap();
-            arguments.add(after.created_at).unwrap();
-        } else if let Some(before) = pag.before.as_ref() {
-            write!(
-                query,
-                "WHERE (id, created_at) < ( ?{}, ?{} ) ",
-                arguments.len() + 1,
-                arguments.len() + 2
-            )?;
-            arguments.add(before.id).unwrap();
-            arguments.add(before.created_at).unwrap();
-        }
-        if pag.last.is_some() {
-            query.push_str("ORDER BY id DESC, created_at DESC ");
+    /// 为事项添加标签，已带有该标签时不做修改
+    pub async fn tag_todo(&self, id: scalar::ID, tag_id: scalar::ID) -> Result<Todo> {
+        let mut tx = self.pool.begin().await?;
+        Self::ensure_tag_exists(&mut tx, tag_id).await?;
+        sqlx::query("INSERT OR IGNORE INTO todo_tags ( todo_id, tag_id ) SELECT id, ?2 FROM todos WHERE id = ?1 AND deleted_at IS NULL")
+            .bind(id)
+            .bind(tag_id)
+            .execute(&mut *tx)
+            .await?;
+        let todo = Self::find_todo(&mut tx, id).await?;
+        if todo.is_some() {
+            tx.commit().await?;
+        }
+
+        self.publish_updated(id, todo)
+    }
+
+    /// 移除事项上的标签，事项本来没有该标签时不做修改
+    pub async fn untag_todo(&self, id: scalar::ID, tag_id: scalar::ID) -> Result<Todo> {
+        let mut tx = self.pool.begin().await?;
+        Self::ensure_tag_exists(&mut tx, tag_id).await?;
+        sqlx::query("DELETE FROM todo_tags WHERE todo_id = ?1 AND tag_id = ?2")
+            .bind(id)
+            .bind(tag_id)
+            .execute(&mut *tx)
+            .await?;
+        let todo = Self::find_todo(&mut tx, id).await?;
+        if todo.is_some() {
+            tx.commit().await?;
+        }
+
+        self.publish_updated(id, todo)
+    }
+
+    async fn ensure_tag_exists(
+        conn: &mut sqlx::SqliteConnection,
+        tag_id: scalar::ID,
+    ) -> Result<()> {
+        let exists =
+            sqlx::query_scalar::<_, bool>("SELECT EXISTS ( SELECT 1 FROM tags WHERE id = ?1 )")
+                .bind(tag_id)
+                .fetch_one(conn)
+                .await?;
+        if exists {
+            Ok(())
         } else {
-            query.push_str("ORDER BY id ASC, created_at ASC ");
+            Err(Error::not_found(Tag::TYPE_NAME, tag_id))
         }
-        write!(query, "LIMIT ?{}", arguments.len() + 1)?;
-        arguments.add(pag.limit()).unwrap();
+    }
 
-        let mut query = sqlx::QueryBuilder::<sqlx::Sqlite>::with_arguments(query, arguments);
+    /// 批量操作在清单不存在时没有可更新的行，需要单独检查才能返回 `NotFound`
+    async fn ensure_list(conn: &mut sqlx::SqliteConnection, list_id: scalar::ID) -> Result<()> {
+        sqlx::query_scalar::<_, i64>("SELECT 1 FROM lists WHERE id = ?1")
+            .bind(list_id)
+            .fetch_optional(conn)
+            .await?
+            .map(|_| ())
+            .ok_or_else(|| Error::not_found(TodoList::TYPE_NAME, list_id))
+    }
+
+    async fn find_todo(conn: &mut sqlx::SqliteConnection, id: scalar::ID) -> Result<Option<Todo>> {
+        let todo = sqlx::query_as::<_, Todo>(&format!(
+            r#"
+            SELECT {TODO_COLUMNS}
+            FROM todos
+            WHERE id = ?1 AND deleted_at IS NULL
+            "#
+        ))
+        .bind(id)
+        .fetch_optional(conn)
+        .await?;
+        Ok(todo)
+    }
+
+    /// 将事项移动到 `anchor` 之前（`before` 为 true）或之后
+    ///
+    /// 新位置取 `anchor` 与其在同一清单中相邻的未删除事项的中点，两者之间没有空隙时先重排该清单再计算
+    pub async fn move_todo(
+        &self,
+        id: scalar::ID,
+        anchor: scalar::ID,
+        before: bool,
+    ) -> Result<Todo> {
+        if id == anchor {
+            return Err(Error::Validation(
+                "Cannot move a todo relative to itself".to_string(),
+            ));
+        }
+        let mut tx = self.pool.begin().await?;
+        let position = match Self::position_next_to(&mut tx, id, anchor, before).await? {
+            Some(position) => position,
+            None => {
+                Self::rebalance_positions(&mut tx, anchor).await?;
+                Self::position_next_to(&mut tx, id, anchor, before)
+                    .await?
+                    .ok_or_else(|| Error::Internal(anyhow!("No gap after rebalancing").into()))?
+            }
+        };
+        let todo = sqlx::query_as::<_, Todo>(&format!(
+            r#"
+            UPDATE todos
+            SET position = ?2, updated_at = UNIXEPOCH()
+            WHERE id = ?1 AND deleted_at IS NULL
+            RETURNING {TODO_COLUMNS}
+            "#
+        ))
+        .bind(id)
+        .bind(position)
+        .fetch_optional(&mut *tx)
+        .await?;
+        // 事项不存在时回滚，不保留可能发生的重排
+        if todo.is_some() {
+            tx.commit().await?;
+        }
+
+        self.publish_updated(id, todo)
+    }
+
+    /// `anchor` 前后可用的位置，与相邻一项之间没有空隙时返回 `None`
+    async fn position_next_to(
+        conn: &mut sqlx::SqliteConnection,
+        id: scalar::ID,
+        anchor: scalar::ID,
+        before: bool,
+    ) -> Result<Option<i64>> {
+        let anchor_position = sqlx::query_scalar::<_, i64>(
+            "SELECT position FROM todos WHERE id = ?1 AND deleted_at IS NULL",
+        )
+        .bind(anchor)
+        .fetch_optional(&mut *conn)
+        .await?
+        .ok_or_else(|| Error::not_found(Todo::TYPE_NAME, anchor))?;
+        // 与 listTodos 一致，位置相同时按 id 排序；只与 `anchor` 所在清单中的事项比较
+        let neighbor = if before {
+            r#"
+            SELECT MAX(position) FROM todos
+            WHERE id <> ?1 AND (position, id) < (?2, ?3) AND deleted_at IS NULL
+                AND list_id = ( SELECT list_id FROM todos WHERE id = ?3 )
+            "#
+        } else {
+            r#"
+            SELECT MIN(position) FROM todos
+            WHERE id <> ?1 AND (position, id) > (?2, ?3) AND deleted_at IS NULL
+                AND list_id = ( SELECT list_id FROM todos WHERE id = ?3 )
+            "#
+        };
+        let neighbor = sqlx::query_scalar::<_, Option<i64>>(neighbor)
+            .bind(id)
+            .bind(anchor_position)
+            .bind(anchor)
+            .fetch_one(&mut *conn)
+            .await?;
+        let position = match neighbor {
+            None if before => Some(anchor_position - POSITION_GAP),
+            None => Some(anchor_position + POSITION_GAP),
+            Some(neighbor) if (neighbor - anchor_position).abs() >= 2 => {
+                Some(neighbor + (anchor_position - neighbor) / 2)
+            }
+            Some(_) => None,
+        };
+        Ok(position)
+    }
+
+    /// 按当前顺序重新分配 `anchor` 所在清单中未删除事项的位置，相邻两项之间恢复为 `POSITION_GAP`
+    async fn rebalance_positions(
+        conn: &mut sqlx::SqliteConnection,
+        anchor: scalar::ID,
+    ) -> Result<()> {
+        sqlx::query(
+            r#"
+            WITH ranked AS (
+                SELECT id, ROW_NUMBER() OVER (ORDER BY position, id) AS rank FROM todos
+                WHERE deleted_at IS NULL
+                    AND list_id = ( SELECT list_id FROM todos WHERE id = ?2 )
+            )
+            UPDATE todos
+            SET position = ranked.rank * ?1
+            FROM ranked
+            WHERE ranked.id = todos.id
+            "#,
+        )
+        .bind(POSITION_GAP)
+        .bind(anchor)
+        .execute(conn)
+        .await?;
+        Ok(())
+    }
+
+    /// 按 ID 批量查询，不存在的 ID 不会出现在结果中
+    pub async fn find_todos(&self, ids: &[scalar::ID]) -> Result<Vec<Todo>> {
+        if ids.is_empty() {
+            return Ok(Vec::new());
+        }
+        let mut query = sqlx::QueryBuilder::<sqlx::Sqlite>::new(format!(
+            "SELECT {TODO_COLUMNS} FROM todos WHERE deleted_at IS NULL AND id IN ( "
+        ));
+        let mut separated = query.separated(", ");
+        for id in ids {
+            separated.push_bind(*id);
+        }
+        separated.push_unseparated(" )");
+        let todos = query.build_query_as::<Todo>().fetch_all(&self.pool).await?;
+        Ok(todos)
+    }
+
+    /// 发布更新事件，`todo` 为 `None` 说明没有匹配 `id` 的事项
+    fn publish_updated(&self, id: scalar::ID, todo: Option<Todo>) -> Result<Todo> {
+        let todo = todo.ok_or_else(|| Error::not_found(Todo::TYPE_NAME, id))?;
+        self.events.publish(TodoEvent::Updated(todo.clone()));
+        Ok(todo)
+    }
+
+    pub async fn list_todos(
+        &self,
+        pag: &relay::Pagination,
+        filter: &TodoFilter,
+        order: &[TodoOrder],
+    ) -> Result<Vec<Todo>> {
+        let mut arguments = sqlx::sqlite::SqliteArguments::default();
+        let conditions = Self::filter_conditions(filter, &mut arguments);
+        let keys = order
+            .iter()
+            .map(|it| (it.field.column(), it.direction))
+            .collect::<Vec<_>>();
+
+        let mut query = keyset::paginate(
+            format!("SELECT {TODO_COLUMNS} FROM todos"),
+            conditions,
+            arguments,
+            &keys,
+            pag,
+        )?;
         let recs = query
             .build_query_as::<Todo>()
             .fetch_all(&self.pool)
@@ -153,10 +1128,322 @@ impl TodoRepository {
         Ok(recs)
     }
 
-    pub async fn total(&self) -> anyhow::Result<i32> {
-        let rec = sqlx::query!("SELECT COUNT(*) as total FROM todos")
+    pub async fn total(&self, filter: &TodoFilter) -> Result<i32> {
+        let mut query = String::from("SELECT COUNT(*) FROM todos ");
+        let mut arguments = sqlx::sqlite::SqliteArguments::default();
+        let conditions = Self::filter_conditions(filter, &mut arguments);
+        if !conditions.is_empty() {
+            query.push_str("WHERE ");
+            query.push_str(&conditions.join(" AND "));
+        }
+
+        let mut query = sqlx::QueryBuilder::<sqlx::Sqlite>::with_arguments(query, arguments);
+        let total = query
+            .build_query_scalar::<i64>()
+            .fetch_one(&self.pool)
+            .await?;
+        Ok(total as i32)
+    }
+
+    /// 按完成状态统计，忽略 `filter.done`
+    pub async fn counts(&self, filter: &TodoFilter) -> Result<TodoCounts> {
+        let filter = TodoFilter {
+            done: None,
+            ..filter.clone()
+        };
+        let mut query = String::from(
+            "SELECT COALESCE(SUM(done = 0), 0), COALESCE(SUM(done = 1), 0) FROM todos ",
+        );
+        let mut arguments = sqlx::sqlite::SqliteArguments::default();
+        let conditions = Self::filter_conditions(&filter, &mut arguments);
+        if !conditions.is_empty() {
+            query.push_str("WHERE ");
+            query.push_str(&conditions.join(" AND "));
+        }
+
+        let mut query = sqlx::QueryBuilder::<sqlx::Sqlite>::with_arguments(query, arguments);
+        let (active, completed) = query
+            .build_query_as::<(i64, i64)>()
+            .fetch_one(&self.pool)
+            .await?;
+        Ok(TodoCounts {
+            active_count: active as i32,
+            completed_count: completed as i32,
+            all_completed: active == 0 && completed > 0,
+        })
+    }
+
+    /// 全文搜索，结果按相关度排序
+    pub async fn search_todos(
+        &self,
+        pag: &relay::Pagination,
+        text: &str,
+    ) -> Result<Vec<(Todo, SearchMatch)>> {
+        use sqlx::Arguments;
+
+        #[derive(sqlx::FromRow)]
+        struct Row {
+            #[sqlx(flatten)]
+            todo: Todo,
+            #[sqlx(flatten)]
+            matched: SearchMatch,
+        }
+
+        let Some(expr) = Self::match_expression(text) else {
+            return Ok(Vec::new());
+        };
+        // FTS5 的辅助函数只能在全文查询中使用，先在子查询中取得相关度和摘要
+        let query = format!(
+            r#"
+            WITH matches AS (
+                SELECT rowid AS id, rank, snippet(todos_fts, 0, CHAR(2), CHAR(3), '…', 16) AS snippet
+                FROM todos_fts
+                WHERE todos_fts MATCH ?1
+            )
+            SELECT {TODO_COLUMNS}, rank, snippet FROM todos JOIN matches USING (id)"#
+        );
+        let mut arguments = sqlx::sqlite::SqliteArguments::default();
+        arguments.add(expr).unwrap();
+        let conditions = vec!["deleted_at IS NULL".to_string()];
+        let keys = [("rank", relay::OrderDirection::Asc)];
+
+        let mut query = keyset::paginate(query, conditions, arguments, &keys, pag)?;
+        let rows = query.build_query_as::<Row>().fetch_all(&self.pool).await?;
+        Ok(rows
+            .into_iter()
+            .map(|mut row| {
+                row.matched.snippet = SearchMatch::highlight(&row.matched.snippet);
+                (row.todo, row.matched)
+            })
+            .collect())
+    }
+
+    pub async fn search_total(&self, text: &str) -> Result<i32> {
+        let Some(expr) = Self::match_expression(text) else {
+            return Ok(0);
+        };
+        let total = sqlx::query_scalar::<_, i64>(
+            r#"
+            SELECT COUNT(*) FROM todos_fts
+            JOIN todos ON todos.id = todos_fts.rowid
+            WHERE todos_fts MATCH ?1 AND todos.deleted_at IS NULL
+            "#,
+        )
+        .bind(expr)
         .fetch_one(&self.pool)
         .await?;
-        Ok(rec.total as i32)
+        Ok(total as i32)
+    }
+
+    /// 将用户输入转为 FTS5 查询表达式：每个词都作为短语加引号，避免输入被解析为 FTS5 语法，
+    /// trigram 分词器无法匹配少于 3 个字符的词，这些词会被忽略
+    fn match_expression(text: &str) -> Option<String> {
+        let terms = text
+            .split_whitespace()
+            .filter(|term| term.chars().count() >= 3)
+            .map(|term| format!("\"{}\"", term.replace('"', "\"\"")))
+            .collect::<Vec<_>>();
+        if terms.is_empty() {
+            None
+        } else {
+            Some(terms.join(" "))
+        }
+    }
+
+    /// 将筛选条件转为 WHERE 子句的各个条件，参数按顺序追加到 `arguments`
+    ///
+    /// 除非 `filter.trashed` 为 true，否则总是排除回收站中的事项
+    fn filter_conditions(
+        filter: &TodoFilter,
+        arguments: &mut sqlx::sqlite::SqliteArguments<'_>,
+    ) -> Vec<String> {
+        use sqlx::Arguments;
+
+        let mut conditions = vec![if filter.trashed {
+            "deleted_at IS NOT NULL".to_string()
+        } else {
+            "deleted_at IS NULL".to_string()
+        }];
+        if let Some(done) = filter.done {
+            conditions.push(format!("done = ?{}", arguments.len() + 1));
+            arguments.add(done).unwrap();
+        }
+        if let Some(text) = filter.description_contains.as_ref() {
+            conditions.push(format!(
+                "description LIKE '%' || ?{} || '%' ESCAPE '\\'",
+                arguments.len() + 1
+            ));
+            let escaped = text
+                .replace('\\', "\\\\")
+                .replace('%', "\\%")
+                .replace('_', "\\_");
+            arguments.add(escaped).unwrap();
+        }
+        if let Some(since) = filter.created_since {
+            conditions.push(format!("created_at >= ?{}", arguments.len() + 1));
+            arguments.add(since).unwrap();
+        }
+        if let Some(before) = filter.created_before {
+            conditions.push(format!("created_at < ?{}", arguments.len() + 1));
+            arguments.add(before).unwrap();
+        }
+        if let Some(before) = filter.due_before {
+            conditions.push(format!("due_at < ?{}", arguments.len() + 1));
+            arguments.add(before).unwrap();
+        }
+        if let Some(overdue) = filter.overdue {
+            conditions.push(if overdue {
+                "( done = FALSE AND due_at < UNIXEPOCH() )".to_string()
+            } else {
+                "( done = TRUE OR due_at IS NULL OR due_at >= UNIXEPOCH() )".to_string()
+            });
+        }
+        if let Some(ids) = filter.ids.as_ref() {
+            // 其他类型的 ID 不可能匹配到待办事项，直接忽略
+            let ids = ids
+                .iter()
+                .filter(|id| id.type_name == Todo::TYPE_NAME)
+                .map(|id| id.id)
+                .collect::<Vec<_>>();
+            if ids.is_empty() {
+                conditions.push("FALSE".to_string());
+            } else {
+                let placeholders = (1..=ids.len())
+                    .map(|i| format!("?{}", arguments.len() + i))
+                    .collect::<Vec<_>>();
+                conditions.push(format!("id IN ( {} )", placeholders.join(", ")));
+                for id in ids {
+                    arguments.add(id).unwrap();
+                }
+            }
+        }
+        if let Some(list_id) = filter.list_id.as_ref() {
+            // 其他类型的 ID 不可能是清单
+            if list_id.type_name == TodoList::TYPE_NAME {
+                conditions.push(format!("list_id = ?{}", arguments.len() + 1));
+                arguments.add(list_id.id).unwrap();
+            } else {
+                conditions.push("FALSE".to_string());
+            }
+        }
+        if let Some(parent_id) = filter.parent_id.as_ref() {
+            if parent_id.type_name == Todo::TYPE_NAME {
+                conditions.push(format!("parent_id = ?{}", arguments.len() + 1));
+                arguments.add(parent_id.id).unwrap();
+            } else {
+                conditions.push("FALSE".to_string());
+            }
+        }
+        if let Some(tags) = filter.tags.as_ref() {
+            conditions.push(Self::tag_condition(tags, arguments));
+        }
+        conditions
+    }
+
+    fn tag_condition(
+        filter: &TodoTagFilter,
+        arguments: &mut sqlx::sqlite::SqliteArguments<'_>,
+    ) -> String {
+        use sqlx::Arguments;
+
+        let mut ids = filter.ids.iter().collect::<Vec<_>>();
+        ids.sort_by_key(|id| (&id.type_name, i64::from(id.id)));
+        ids.dedup();
+        // 与 `ids` 一样忽略其他类型的 ID，但 `ALL` 要求的标签中混入了它们时不可能匹配
+        let (tag_ids, others): (Vec<_>, Vec<_>) = ids
+            .into_iter()
+            .partition(|id| id.type_name == Tag::TYPE_NAME);
+        match filter.mode {
+            TagMatch::Any if tag_ids.is_empty() => return "FALSE".to_string(),
+            TagMatch::All if !others.is_empty() => return "FALSE".to_string(),
+            TagMatch::All if tag_ids.is_empty() => return "TRUE".to_string(),
+            _ => {}
+        }
+        let placeholders = (1..=tag_ids.len())
+            .map(|i| format!("?{}", arguments.len() + i))
+            .collect::<Vec<_>>()
+            .join(", ");
+        for id in tag_ids.iter() {
+            arguments.add(id.id).unwrap();
+        }
+        match filter.mode {
+            TagMatch::Any => format!(
+                "id IN ( SELECT todo_id FROM todo_tags WHERE tag_id IN ( {placeholders} ) )"
+            ),
+            TagMatch::All => {
+                let condition = format!(
+                    "id IN ( SELECT todo_id FROM todo_tags WHERE tag_id IN ( {placeholders} ) \
+                     GROUP BY todo_id HAVING COUNT(*) = ?{} )",
+                    arguments.len() + 1
+                );
+                arguments.add(tag_ids.len() as i64).unwrap();
+                condition
+            }
+        }
+    }
+}
+
+impl BatchFn<scalar::ID, Todo> for TodoRepository {
+    async fn load(&self, keys: &[scalar::ID]) -> Result<HashMap<scalar::ID, Todo>> {
+        let todos = self.find_todos(keys).await?;
+        Ok(todos.into_iter().map(|todo| (todo.id, todo)).collect())
+    }
+}
+
+/// 子孙事项数，供 `Todo.descendantCount` 批量加载
+impl BatchFn<scalar::ID, i32> for TodoRepository {
+    async fn load(&self, keys: &[scalar::ID]) -> Result<HashMap<scalar::ID, i32>> {
+        self.descendant_counts(keys).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::pin::pin;
+
+    use juniper::futures::{FutureExt, StreamExt};
+
+    use super::*;
+
+    async fn repository() -> TodoRepository {
+        let pool = sqlx::sqlite::SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        sqlx::migrate!("./migrations").run(&pool).await.unwrap();
+        TodoRepository::new(pool, EventHub::default())
+    }
+
+    #[tokio::test]
+    async fn undo_skips_operations_of_purged_todos() {
+        let repo = repository().await;
+        let inbox = scalar::ID::from(1);
+        let a = repo.add_todo(inbox, String::from("a")).await.unwrap();
+        let b = repo.add_todo(inbox, String::from("b")).await.unwrap();
+        repo.toggle_all(inbox, true).await.unwrap();
+        repo.remove_todo(a.id).await.unwrap();
+
+        let mut cleared = pin!(repo.events.subscribe(|event| match event {
+            TodoEvent::Cleared(ids) => Some(ids),
+            _ => None,
+        }));
+        assert_eq!(repo.empty_trash().await.unwrap(), vec![a.id]);
+        assert_eq!(cleared.next().now_or_never().flatten(), Some(vec![a.id]));
+
+        // 只涉及 a 的添加和删除被移除，全部切换只保留 b 的变更
+        let operations = crate::repositories::OperationRepository::new(repo.pool.clone());
+        assert_eq!(operations.total().await.unwrap(), 2);
+        let (operation, todos) = repo.undo().await.unwrap().unwrap();
+        assert_eq!(operation.kind, OperationKind::ToggleAll);
+        assert_eq!(
+            todos.iter().map(|it| (it.id, it.done)).collect::<Vec<_>>(),
+            vec![(b.id, false)]
+        );
+        let (operation, todos) = repo.undo().await.unwrap().unwrap();
+        assert_eq!(operation.kind, OperationKind::AddTodo);
+        assert_eq!(todos[0].id, b.id);
+        assert!(todos[0].deleted_at.is_some());
+        assert!(repo.undo().await.unwrap().is_none());
     }
 }
@@ -0,0 +1,46 @@
+use std::path::Path;
+use std::time::Duration;
+
+use anyhow::Context;
+use serde::Deserialize;
+
+use crate::trash::PurgePolicy;
+
+/// 设置文件名，位于应用数据目录下
+const SETTINGS_FILE: &str = "settings.json";
+
+/// 应用设置，读取自应用数据目录下的 `settings.json`；文件不存在或缺少的项使用默认值
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase", deny_unknown_fields)]
+pub struct Settings {
+    /// 分页游标的有效期（秒），不设置时游标不过期
+    pub cursor_ttl_secs: Option<u64>,
+    /// 事项在回收站中保留的天数，不设置时使用 `PurgePolicy` 的默认值
+    pub trash_retention_days: Option<u64>,
+}
+
+impl Settings {
+    pub fn load(dir: &Path) -> anyhow::Result<Self> {
+        let path = dir.join(SETTINGS_FILE);
+        match std::fs::read(&path) {
+            Ok(bytes) => serde_json::from_slice(&bytes)
+                .with_context(|| format!("Invalid settings file {}", path.display())),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => {
+                Err(e).with_context(|| format!("Failed to read the settings {}", path.display()))
+            }
+        }
+    }
+
+    pub fn cursor_ttl(&self) -> Option<Duration> {
+        self.cursor_ttl_secs.map(Duration::from_secs)
+    }
+
+    pub fn purge_policy(&self) -> PurgePolicy {
+        let mut policy = PurgePolicy::default();
+        if let Some(days) = self.trash_retention_days {
+            policy.retention = Duration::from_secs(days.saturating_mul(24 * 60 * 60));
+        }
+        policy
+    }
+}
@@ -1,14 +1,60 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+
 use sqlx::SqlitePool;
-use crate::graphql;
+use tauri::async_runtime::JoinHandle;
+
+use crate::events::EventHub;
+use crate::graphql::{self, limits::QueryLimits, persisted::PersistedQueries};
 
 pub struct AppState {
     pub pool: SqlitePool,
-    pub schema: graphql::Schema
+    pub schema: Arc<graphql::Schema>,
+    pub events: EventHub,
+    pub subscriptions: Subscriptions,
+    pub persisted_queries: PersistedQueries,
+    pub limits: QueryLimits,
+}
+
+/// 正在运行的 GraphQL 订阅任务，按订阅 ID 索引
+#[derive(Default)]
+pub struct Subscriptions {
+    next_id: AtomicU32,
+    tasks: Mutex<HashMap<u32, JoinHandle<()>>>,
+}
+
+impl Subscriptions {
+    pub fn insert(&self, task: JoinHandle<()>) -> u32 {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let mut tasks = self.tasks.lock().unwrap();
+        // 顺带清理已经自然结束的订阅（例如窗口关闭导致 Channel 失效）
+        tasks.retain(|_, task| !task.inner().is_finished());
+        tasks.insert(id, task);
+        id
+    }
+
+    pub fn remove(&self, id: u32) -> bool {
+        match self.tasks.lock().unwrap().remove(&id) {
+            Some(task) => {
+                task.abort();
+                true
+            }
+            None => false,
+        }
+    }
 }
 
-pub fn build_app_state(pool: SqlitePool) -> AppState{
+pub fn build_app_state(pool: SqlitePool) -> AppState {
+    let schema = graphql::create_schema();
+    #[cfg(debug_assertions)]
+    graphql::write_sdl(&schema).unwrap();
     AppState {
         pool,
-        schema: graphql::create_schema(),
+        schema: Arc::new(schema),
+        events: EventHub::default(),
+        subscriptions: Subscriptions::default(),
+        persisted_queries: PersistedQueries::new(256),
+        limits: QueryLimits::default(),
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,42 @@
+use std::time::Duration;
+
+use sqlx::SqlitePool;
+
+use crate::{error::Result, events::EventHub, repositories::TodoRepository};
+
+/// 回收站的自动清理策略
+#[derive(Debug, Clone)]
+pub struct PurgePolicy {
+    /// 事项在回收站中保留的时长，超过后被彻底删除
+    pub retention: Duration,
+    /// 两次清理之间的间隔
+    pub interval: Duration,
+}
+
+impl Default for PurgePolicy {
+    fn default() -> Self {
+        Self {
+            retention: Duration::from_secs(30 * 24 * 60 * 60),
+            interval: Duration::from_secs(60 * 60),
+        }
+    }
+}
+
+/// 按 `policy` 定期彻底删除回收站中过期的事项，启动时立即执行一次
+///
+/// 与 `emptyTrash` 相同，被清理的事项通过 `todosCleared` 推送，以便更新回收站列表
+pub async fn run_purge(pool: SqlitePool, events: EventHub, policy: PurgePolicy) {
+    let repo = TodoRepository::new(pool, events);
+    loop {
+        if let Err(e) = purge_expired(&repo, &policy).await {
+            eprintln!("Failed to purge the trash: {e:?}");
+        }
+        tokio::time::sleep(policy.interval).await;
+    }
+}
+
+async fn purge_expired(repo: &TodoRepository, policy: &PurgePolicy) -> Result<u64> {
+    let now = chrono::Utc::now().timestamp();
+    let before = now - policy.retention.as_secs() as i64;
+    repo.purge_trash(before.into()).await
+}
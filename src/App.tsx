@@ -1,16 +1,67 @@
-import { createEffect, createMemo, createSignal, For, onCleanup, Show } from 'solid-js';
-import { invoke } from '@tauri-apps/api/core';
+import { createEffect, createSignal, For, onCleanup, Show } from 'solid-js';
+import { Channel, invoke } from '@tauri-apps/api/core';
+import { listen } from '@tauri-apps/api/event';
+
+type GraphQLRequest = { query: string, variables?: Record<string, unknown> };
+type GraphQLResponse<T> = {
+    data: T,
+    errors?: { message: string, extensions?: { code?: string } }[]
+};
+
+const sha256 = async (text: string) => {
+    const digest = await crypto.subtle.digest('SHA-256', new TextEncoder().encode(text));
+    return Array.from(new Uint8Array(digest), b => b.toString(16).padStart(2, '0')).join('');
+};
+
+/** APQ：先只发送查询文本的哈希，后端没有缓存该查询时再附带完整文本重试 */
+const invokePersisted = async (body: GraphQLRequest | GraphQLRequest[]): Promise<unknown> => {
+    const requests = Array.isArray(body) ? body : [body];
+    const persisted = await Promise.all(requests.map(async ({ query, variables }) => ({
+        variables,
+        extensions: { persistedQuery: { version: 1, sha256Hash: await sha256(query) } }
+    })));
+    const send = (payload: Record<string, unknown>[]) =>
+        invoke('graphql', { body: Array.isArray(body) ? payload : payload[0] });
+    try {
+        return await send(persisted);
+    } catch (e) {
+        const responses = (Array.isArray(e) ? e : [e]) as GraphQLResponse<unknown>[];
+        const notFound = responses.some(res => res?.errors?.some(
+            err => err.extensions?.code === 'PERSISTED_QUERY_NOT_FOUND'
+        ));
+        if (!notFound) {
+            throw e;
+        }
+        return send(persisted.map((it, i) => ({ ...it, query: requests[i].query })));
+    }
+};
 
 const graphql = async <T = unknown>(
     query: string,
     variables: Record<string, unknown>
-): Promise<{ data: T }> => {
-    return invoke('graphql', {
-        body: {
-            query,
-            variables
+): Promise<GraphQLResponse<T>> => {
+    return await invokePersisted({ query, variables }) as GraphQLResponse<T>;
+};
+
+const subscribe = async <T = unknown>(
+    query: string,
+    onData: (data: T) => void
+): Promise<() => Promise<void>> => {
+    const onEvent = new Channel<{ data?: T, errors?: unknown[] }>();
+    onEvent.onmessage = (response) => {
+        if (response.errors) {
+            console.error(response.errors);
+        } else if (response.data) {
+            onData(response.data);
         }
+    };
+    const id = await invoke<number>('graphql_subscribe', {
+        body: { query },
+        onEvent
     });
+    return async () => {
+        await invoke('graphql_unsubscribe', { id });
+    };
 };
 
 type PageInfo = {
@@ -24,9 +75,14 @@ type Connection<N> = {
         node: N,
         cursor: string
     }[]
-    // totalCount: number
+    totalCount: number
     pageInfo: PageInfo
 }
+type TodoConnection = Connection<Todo> & {
+    activeCount: number
+    completedCount: number
+    allCompleted: boolean
+}
 
 type Todo = {
     id: string,
@@ -35,6 +91,36 @@ type Todo = {
     created_at: string
 }
 
+type TodoList = {
+    id: string
+    name: string
+    color?: string
+}
+
+/** `todo-reminder` 事件的负载，时间为 Unix 秒 */
+type Reminder = {
+    id: string
+    description: string
+    dueAt?: number
+    remindAt?: number
+}
+
+type TodoFilter = {
+    done?: boolean
+    descriptionContains?: string
+    createdSince?: string
+    createdBefore?: string
+    dueBefore?: string
+    overdue?: boolean
+    ids?: string[]
+    listId?: string
+}
+
+type TodoOrder = {
+    field: 'CREATED_AT' | 'DESCRIPTION' | 'DONE' | 'UPDATED_AT' | 'POSITION'
+    direction?: 'ASC' | 'DESC'
+}
+
 type Filter = 'all' | 'active' | 'completed';
 
 const services = {
@@ -43,78 +129,135 @@ const services = {
         after?: string
         last?: number
         before?: string
+        filter?: TodoFilter
+        orderBy?: TodoOrder[]
     } = {}) => {
-        const list = await graphql<{ listTodos: Connection<Todo> }>(`
-          query ListTodos($first: Int, $after: Cursor, $last: Int, $before: Cursor){ 
-            listTodos(first: $first, after: $after, last: $last, before: $before) {
-              edges {
-                node {
-                  id
-                  description
-                  done
-                  createdAt
+        const res = await graphql<{ listTodos: TodoConnection }>(`
+              query ListTodos($first: Int, $after: Cursor, $last: Int, $before: Cursor, $filter: TodoFilter, $orderBy: [TodoOrder!]){ 
+                listTodos(first: $first, after: $after, last: $last, before: $before, filter: $filter, orderBy: $orderBy) {
+                  edges {
+                    node {
+                      id
+                      description
+                      done
+                      createdAt
+                    }
+                    cursor
+                  }
+                  totalCount
+                  activeCount
+                  completedCount
+                  allCompleted
+                  pageInfo {
+                    hasPreviousPage
+                    hasNextPage
+                    startCursor
+                    endCursor
+                  }
                 }
-                cursor
-              }
-              # totalCount
-              pageInfo {
-                hasPreviousPage
-                hasNextPage
-                startCursor
-                endCursor
               }
+            `, params);
+        return res.data;
+    },
+    lists: async () => {
+        const res = await graphql<{ lists: { nodes: TodoList[] } }>(`
+            query Lists {
+                lists {
+                    nodes { id name color }
+                }
             }
-          }
-      `, params);
-        return list.data.listTodos;
+        `, {});
+        return res.data.lists.nodes;
     },
-    addTodo: async (description: string) => {
-        const res = await graphql<string>(`
-            mutation AddTodo($description: String!) {
-                addTodo(description: $description)
+    addTodo: async (description: string, listId?: string) => {
+        const res = await graphql<{ addTodo: { todo: Todo } }>(`
+            mutation AddTodo($input: AddTodoInput!) {
+                addTodo(input: $input) {
+                    todo { id description done }
+                }
             }
-        `, { description });
-        return res.data;
+        `, { input: { description, listId } });
+        return res.data.addTodo.todo;
     },
     completeTodo: async (id: string, done: boolean) => {
-        const res = await graphql<boolean>(`
-            mutation CompleteTodo($id: ID!, $done: Boolean!) {
-                completeTodo(id: $id, done: $done)
+        const res = await graphql<{ completeTodo: { todo: Todo } }>(`
+            mutation CompleteTodo($input: CompleteTodoInput!) {
+                completeTodo(input: $input) {
+                    todo { id description done }
+                }
             }
-        `, { id, done });
-        return res.data;
+        `, { input: { id, done } });
+        return res.data.completeTodo.todo;
     },
-    toggleAll: async (done: boolean) => {
-        const res = await graphql<boolean>(`
-            mutation ToggleAll($done: Boolean!) {
-                toggleAll(done: $done)
+    toggleAll: async (done: boolean, listId?: string) => {
+        const res = await graphql<{ toggleAll: { updatedTodos: Todo[] } }>(`
+            mutation ToggleAll($input: ToggleAllInput!) {
+                toggleAll(input: $input) {
+                    updatedTodos { id description done }
+                }
             }
-        `, { done });
-        return res.data;
+        `, { input: { done, listId } });
+        return res.data.toggleAll.updatedTodos;
     },
-    clearCompleted: async () => {
-        const res = await graphql<boolean>(`
-            mutation ClearCompleted {
-                clearCompleted
+    clearCompleted: async (listId?: string) => {
+        const res = await graphql<{ clearCompleted: { deletedTodoIds: string[] } }>(`
+            mutation ClearCompleted($input: ClearCompletedInput) {
+                clearCompleted(input: $input) {
+                    deletedTodoIds
+                }
             }
-        `, {});
-        return res.data;
+        `, { input: { listId } });
+        return res.data.clearCompleted.deletedTodoIds;
     },
     editTodo: async (id: string, description: string) => {
-        const res = await graphql<string>(`
-            mutation EditTodo($id: ID!, $description: String!) {
-                editTodo(id: $id, description: $description)
+        const res = await graphql<{ editTodo: { todo: Todo } }>(`
+            mutation EditTodo($input: EditTodoInput!) {
+                editTodo(input: $input) {
+                    todo { id description done }
+                }
             }
-        `, { id, description });
-        return res.data;
+        `, { input: { id, description } });
+        return res.data.editTodo.todo;
     },
     removeTodo: async (id: string) => {
-        const res = await graphql<boolean>(`
-            mutation RemoveTodo($id: ID!) {
-                removeTodo(id: $id)
+        const res = await graphql<{ removeTodo: { deletedTodoId: string } }>(`
+            mutation RemoveTodo($input: RemoveTodoInput!) {
+                removeTodo(input: $input) {
+                    deletedTodoId
+                }
             }
-        `, { id });
-        return res.data;
+        `, { input: { id } });
+        return res.data.removeTodo.deletedTodoId;
+    },
+    moveTodo: async (id: string, target: { beforeId: string } | { afterId: string }) => {
+        const res = await graphql<{ moveTodo: { todo: Todo } }>(`
+            mutation MoveTodo($input: MoveTodoInput!) {
+                moveTodo(input: $input) {
+                    todo { id description done }
+                }
+            }
+        `, { input: { id, ...target } });
+        return res.data.moveTodo.todo;
+    },
+    undo: async () => {
+        const res = await graphql<{ undo: { operation: { id: string } | null } }>(`
+            mutation Undo {
+                undo {
+                    operation { id kind }
+                }
+            }
+        `, {});
+        return res.data.undo.operation;
+    },
+    redo: async () => {
+        const res = await graphql<{ redo: { operation: { id: string } | null } }>(`
+            mutation Redo {
+                redo {
+                    operation { id kind }
+                }
+            }
+        `, {});
+        return res.data.redo.operation;
     }
 };
 
@@ -134,12 +277,31 @@ const setFocus = (el: HTMLElement) => setTimeout(() => el.focus());
 function App() {
     const [submitting, setSubmitting] = createSignal(false);
     const [todos, setTodos] = createSignal<Todo[]>([]);
+    const [remainingCount, setRemainingCount] = createSignal(0);
+    const [completedCount, setCompletedCount] = createSignal(0);
+    const [allCompleted, setAllCompleted] = createSignal(false);
     const [editing, setEditing] = createSignal<string | undefined>(undefined);
     const [showMode, setShowMode] = createSignal<Filter>('all');
+    const [dragging, setDragging] = createSignal<string | undefined>(undefined);
+    const [lists, setLists] = createSignal<TodoList[]>([]);
+    // 未选择时后端使用默认清单 Inbox
+    const [currentList, setCurrentList] = createSignal<string | undefined>(undefined);
 
     const listTodos = async () => {
-        const list = await services.listTodos({ first: 999 });
-        setTodos(list.edges.map(it => it.node));
+        const filter: Record<Filter, TodoFilter> = {
+            all: {},
+            active: { done: false },
+            completed: { done: true }
+        };
+        const data = await services.listTodos({
+            first: 999,
+            filter: { ...filter[showMode()], listId: currentList() },
+            orderBy: [{ field: 'POSITION' }]
+        });
+        setTodos(data.listTodos.edges.map(it => it.node));
+        setRemainingCount(data.listTodos.activeCount);
+        setCompletedCount(data.listTodos.completedCount);
+        setAllCompleted(data.listTodos.allCompleted);
     };
     const addTodo = async ({ target, code }: KeyboardEvent) => {
         const description = (target as HTMLInputElement).value.trim();
@@ -148,7 +310,7 @@ function App() {
         }
         setSubmitting(true);
         try {
-            await services.addTodo(description);
+            await services.addTodo(description, currentList());
             (target as HTMLInputElement).value = '';
             await listTodos();
         } catch (e) {
@@ -173,11 +335,24 @@ function App() {
         await listTodos();
     };
     const toggleAll = async (done: boolean) => {
-        await services.toggleAll(done);
+        await services.toggleAll(done, currentList());
         await listTodos();
     };
     const clearCompleted = async () => {
-        await services.clearCompleted();
+        await services.clearCompleted(currentList());
+        await listTodos();
+    };
+    // 向下拖动时放到目标之后，向上拖动时放到目标之前
+    const drop = async (targetId: string, e: DragEvent) => {
+        e.preventDefault();
+        const id = dragging();
+        setDragging(undefined);
+        if (!id || id === targetId) {
+            return;
+        }
+        const ids = todos().map(it => it.id);
+        const target = ids.indexOf(id) < ids.indexOf(targetId) ? { afterId: targetId } : { beforeId: targetId };
+        await services.moveTodo(id, target);
         await listTodos();
     };
     const doneEditing = (todoId: string, e: KeyboardEvent) => {
@@ -187,21 +362,45 @@ function App() {
             setEditing(undefined);
         }
     };
-    const filteredTodos = createMemo(() => {
-        switch (showMode()) {
-            case 'all':
-                return todos();
-            case 'active':
-                return todos().filter(it => !it.done);
-            case 'completed':
-                return todos().filter(it => it.done);
-        }
-    });
-    const remainingCount = createMemo(() => todos().filter(it => !it.done).length);
-
+    // 切换筛选标签或清单时重新查询
     createEffect(async () => {
+        showMode();
+        currentList();
         await listTodos();
     });
+    services.lists().then(lists => {
+        setLists(lists);
+        setCurrentList(lists[0]?.id);
+    }).catch(console.error);
+
+    // 其他窗口修改数据后通过订阅同步
+    const subscriptions = [
+        'subscription TodoAdded { todoAdded { id } }',
+        'subscription TodoUpdated { todoUpdated { id } }',
+        'subscription TodoRemoved { todoRemoved }',
+        'subscription TodosCleared { todosCleared }'
+    ].map(query => subscribe(query, () => listTodos().catch(console.error)));
+    onCleanup(() => subscriptions.forEach(it => it.then(unsubscribe => unsubscribe()).catch(console.error)));
+
+    // 提醒由后端的调度任务在到期时推送
+    const unlistenReminder = listen<Reminder>('todo-reminder', ({ payload }) => alert(`Reminder: ${payload.description}`));
+    onCleanup(() => unlistenReminder.then(unlisten => unlisten()).catch(console.error));
+
+    // 输入框内保留浏览器自带的文本撤销
+    const historyHandler = (e: KeyboardEvent) => {
+        if (!(e.ctrlKey || e.metaKey) || e.target instanceof HTMLInputElement) {
+            return;
+        }
+        const key = e.key.toLowerCase();
+        const redo = key === 'y' || (key === 'z' && e.shiftKey);
+        if (key !== 'z' && !redo) {
+            return;
+        }
+        e.preventDefault();
+        (redo ? services.redo() : services.undo()).then(listTodos).catch(console.error);
+    };
+    window.addEventListener('keydown', historyHandler);
+    onCleanup(() => window.removeEventListener('keydown', historyHandler));
 
     const locationHandler = () => setShowMode(location.hash.slice(2) as Filter || 'all');
     window.addEventListener('hashchange', locationHandler);
@@ -212,18 +411,29 @@ function App() {
         <section class="todoapp">
             <header class="header">
                 <h1>todos</h1>
+                <Show when={lists().length > 1}>
+                    <select class="list-select" value={currentList()}
+                            onChange={({ target: { value } }) => setCurrentList(value)}>
+                        <For each={lists()}>
+                            {(list) => <option value={list.id} style={{ color: list.color }}>{list.name}</option>}
+                        </For>
+                    </select>
+                </Show>
                 <input type="text" class="new-todo" placeholder="What needs to be done?" onKeyDown={addTodo}
                        disabled={submitting()} />
             </header>
-            <Show when={todos().length > 0}>
+            <Show when={remainingCount() + completedCount() > 0}>
                 <section class="main">
-                    <input id="toggle-all" class="toggle-all" checked={!remainingCount()} type="checkbox"
+                    <input id="toggle-all" class="toggle-all" checked={allCompleted()} type="checkbox"
                            onInput={({ target: { checked } }) => toggleAll(checked)} />
                     <label for="toggle-all" />
                     <ul class="todo-list">
-                        <For each={filteredTodos()}>
+                        <For each={todos()}>
                             {(todo) => (
-                                <li class="todo" classList={{ editing: editing() === todo.id, completed: todo.done }}>
+                                <li class="todo" classList={{ editing: editing() === todo.id, completed: todo.done }}
+                                    draggable={editing() !== todo.id} onDragStart={[setDragging, todo.id]}
+                                    onDragEnd={() => setDragging(undefined)} onDragOver={e => e.preventDefault()}
+                                    onDrop={[drop, todo.id]}>
                                     <div class="view">
                                         <input type="checkbox" class="toggle" checked={todo.done}
                                                onInput={[toggle, [todo.id, !todo.done]]} />
@@ -256,7 +466,7 @@ function App() {
                             <a href="#/completed" classList={{ selected: showMode() === 'completed' }}>Completed</a>
                         </li>
                     </ul>
-                    <Show when={remainingCount() !== todos().length}>
+                    <Show when={completedCount() > 0}>
                         <button class="clear-completed" onClick={clearCompleted}>
                             Clear completed
                         </button>